@@ -0,0 +1,194 @@
+//! Per-frame statistics of the basins of attraction, computed from the root index of each pixel, and their export
+//! as CSV or JSON, one file per frame.
+//!
+//! The pixels that did not converge are handled like an extra basin, labelled `none`. Areas are fractions of the
+//! image, perimeters are numbers of edge pixels (see `draw::is_edge`) and components are 4-connected.
+
+use std::io::{self, Write};
+use super::draw::is_edge;
+use super::render::Layers;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasinStats {
+    /// Index of the root, `None` for the pixels that did not converge
+    pub root: Option<usize>,
+    /// Fraction of the pixels in the basin
+    pub area: f64,
+    /// Number of connected components
+    pub components: usize,
+    /// Number of pixels of the basin on its boundary
+    pub perimeter: usize,
+    pub mean_iterations: f64,
+    pub max_iterations: usize,
+}
+
+/// The statistics of every basin of a frame, the unconverged pixels coming last
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameStats {
+    pub frame: usize,
+    pub basins: Vec<BasinStats>,
+}
+
+impl FrameStats {
+    pub fn new(frame: usize, layers: &Layers, roots: usize) -> Self {
+        let (width, height) = (layers.width, layers.height);
+        let pixels = (width * height) as f64;
+        let mut basins = (0..=roots).map(|root| BasinStats {
+            root: (root < roots).then_some(root),
+            area: 0.0,
+            components: 0,
+            perimeter: 0,
+            mean_iterations: 0.0,
+            max_iterations: 0,
+        }).collect::<Vec<_>>();
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = x + y * width;
+                let basin = &mut basins[layers.table[i].min(roots)];
+                basin.area += 1.0;
+                basin.mean_iterations += layers.iterations[i] as f64;
+                basin.max_iterations = basin.max_iterations.max(layers.iterations[i]);
+                if is_edge(x, y, &layers.table, width, height) {
+                    basin.perimeter += 1;
+                }
+            }
+        }
+        for (root, components) in components(&layers.table, width, height, roots).into_iter().enumerate() {
+            basins[root].components = components;
+        }
+        for basin in basins.iter_mut() {
+            if basin.area > 0.0 {
+                basin.mean_iterations /= basin.area;
+            }
+            basin.area /= pixels;
+        }
+
+        Self { frame, basins }
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        for basin in self.basins.iter() {
+            writeln!(
+                out,
+                "{},{},{:.6},{},{},{:.3},{}",
+                self.frame,
+                root_label(basin.root),
+                basin.area,
+                basin.components,
+                basin.perimeter,
+                basin.mean_iterations,
+                basin.max_iterations
+            )?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> String {
+        let basins = self.basins.iter().map(|basin| format!(
+            "{{\"root\": {}, \"area\": {:.6}, \"components\": {}, \"perimeter\": {}, \"mean_iterations\": {:.3}, \"max_iterations\": {}}}",
+            basin.root.map_or(String::from("null"), |root| root.to_string()),
+            basin.area,
+            basin.components,
+            basin.perimeter,
+            basin.mean_iterations,
+            basin.max_iterations
+        )).collect::<Vec<_>>();
+        format!("{{\"frame\": {}, \"basins\": [{}]}}", self.frame, basins.join(", "))
+    }
+}
+
+fn root_label(root: Option<usize>) -> String {
+    root.map_or(String::from("none"), |root| root.to_string())
+}
+
+/// Counts the 4-connected components of each basin, the unconverged pixels being counted as basin `roots`
+fn components(table: &[usize], width: usize, height: usize, roots: usize) -> Vec<usize> {
+    let mut res = vec![0; roots + 1];
+    let mut seen = vec![false; table.len()];
+    let mut stack = Vec::new();
+
+    for start in 0..table.len() {
+        if seen[start] {
+            continue;
+        }
+        let root = table[start];
+        res[root.min(roots)] += 1;
+        seen[start] = true;
+        stack.push(start);
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for j in neighbors.into_iter().flatten() {
+                if !seen[j] && table[j] == root {
+                    seen[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+    res
+}
+
+/// Writes the statistics of a frame to `path`, as CSV with one row per basin, or as JSON if its extension is `.json`
+pub fn write(path: &str, frame: &FrameStats) -> io::Result<()> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+
+    if path.to_ascii_lowercase().ends_with(".json") {
+        writeln!(out, "{}", frame.to_json())?;
+    } else {
+        writeln!(out, "frame,root,area,components,perimeter,mean_iterations,max_iterations")?;
+        frame.write_csv(&mut out)?;
+    }
+    out.flush()
+}
+
+#[test]
+fn test_basin_stats() {
+    use num::complex::Complex;
+    use distance_transform::GenericGrid;
+
+    // Root 0 on the left, root 1 on the right with an island of root 0, and an unconverged pixel
+    let table = vec![
+        0, 0, 1, 1, 1,
+        0, 0, 1, 0, 1,
+        0, 0, 1, 1, 2,
+    ];
+    let layers = Layers {
+        width: 5,
+        height: 3,
+        iterations: (0..15).collect(),
+        values: vec![Complex::new(0.0, 0.0); 15],
+        distance: GenericGrid::new(5, 3),
+        proximity: GenericGrid::new(5, 3),
+        nabla_dx: GenericGrid::new(5, 3),
+        nabla_dy: GenericGrid::new(5, 3),
+        table,
+    };
+
+    let stats = FrameStats::new(7, &layers, 2);
+    assert_eq!(stats.basins.len(), 3);
+    let [zero, one, none] = [&stats.basins[0], &stats.basins[1], &stats.basins[2]];
+    assert_eq!(zero.area, 7.0 / 15.0);
+    assert_eq!(zero.components, 2);
+    assert_eq!(one.components, 1);
+    assert_eq!(none.root, None);
+    assert_eq!((none.components, none.max_iterations, none.mean_iterations), (1, 14, 14.0));
+    assert_eq!(zero.max_iterations, 11);
+    // Every pixel of root 0 but (0, 0), (0, 1) and (0, 2) touches another basin
+    assert_eq!(zero.perimeter, 4);
+
+    let mut csv = Vec::new();
+    stats.write_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().last(), Some("7,none,0.066667,1,1,14.000,14"));
+    assert!(stats.to_json().starts_with("{\"frame\": 7, \"basins\": [{\"root\": 0, \"area\": 0.466667"));
+}
@@ -0,0 +1,496 @@
+//! Command-line argument parsing
+
+use std::ops::Range;
+use num::complex::Complex;
+use super::output::OutputFormat;
+use super::sink::{Target, Playback};
+use super::render::Method;
+use super::overlay::Overlay;
+use super::scene::parse_complex;
+use super::sweep::Axis;
+
+pub const USAGE: &str = "\
+Usage: newton [animate] [OPTIONS]
+       newton render <SCENE> [OPTIONS]
+       newton view [SCENE]
+       newton analyze [SCENE] [OPTIONS]
+       newton dimension [SCENE] [OPTIONS]
+       newton trace <POINT> [SCENE] [OPTIONS]
+       newton explore [COUNT] [OPTIONS]
+       newton sweep <AXIS> [AXIS] [SCENE] [OPTIONS]
+
+Commands:
+    animate               Render the built-in animation (default)
+    render                Render a single image from a scene file, see `src/scene.rs` for its format
+    view                  Open an interactive viewer, requires the `viewer` feature
+    analyze               Iterate the critical points of the Newton map of each frame of the animation, or of a scene file,
+                          and report the frames where some fall into an attracting cycle instead of a root
+    dimension             Estimate the box-counting dimension of the basin boundary of each frame of the animation,
+                          or of a scene file
+    trace                 Print the orbit of the Newton-Raphson method from POINT, like 0.5+0.2i, on the first selected
+                          frame of the animation or on a scene file
+    explore               Generate COUNT random scenes [default: 24], score their thumbnails and write a contact sheet
+                          and a scene file per candidate to output/explore/
+    sweep                 Render a grid of thumbnails of the first selected frame of the animation or of a scene file,
+                          one parameter changing along the columns and another along the rows, labelled with their
+                          values; an AXIS is like `relaxation=1..2/5` or `epsilon=0.01,0.02`, and the parameters are
+                          relaxation, epsilon, shadow_strength, iterations and scale; written to
+                          output/sweep-<frame>.png unless --output is given
+
+Options:
+    --frames <RANGE>      Only render the frames in RANGE, like `100..200`, `100..` or `42`
+    --shard <K/N>         Split the animation into N interleaved shards and only render the K-th one (1 <= K <= N)
+    --force               Render frames again even if a valid image already exists for them
+    -o, --output <PATH>   Where to write the frames [default: output/{}.png]
+                          With a .gif, .apng or .y4m extension, the whole animation is encoded into that file;
+                          otherwise one image is written per frame, and `{}` is replaced with the frame number
+    --format <FORMAT>     Image format, one of png, png16, tiff, exr, hdr, webp [default: guessed from the extension]
+    --pipe <COMMAND>      Stream the frames as y4m into the standard input of COMMAND, like `ffmpeg -i - out.mp4`
+    --fps <N>             Frame rate of the animation [default: 30]
+    --loop <N>            Number of times the animation plays, 0 to loop forever [default: 0]
+    --method <METHOD>     How pixels are computed: brute, or progressive or subdivide to skip the interior of the basins
+                          [default: brute]
+    --verify              With --method, also run the brute-force pass and report the pixels that differ
+    --compare-forms       Also render with both forms of the Newton step (see `NewtonForm`), reporting how much
+                          they differ and writing the pixels that do to output/compare/<frame>.png
+    --raw                 Also export the intermediate buffers of each frame as .npy files in output/raw/<frame>/
+    --basin-stats <PATH>  Write the area, components, perimeter and iterations of each basin of each rendered frame
+                          to PATH, where {} is replaced with the frame number, as JSON if it ends with .json and as
+                          CSV otherwise
+    --refine <N>          With dimension, also count boxes down to 1/2^N pixel by computing the corners of sub-pixel
+                          cells in the crossed boxes of 1 pixel [default: 0]
+    --seed <N>            With explore, seed of the random scenes [default: 0]
+    --overlay             With trace, also render the frame and draw the orbit over it, to output/trace-<frame>.png
+                          unless --output is given
+    --overlays <LIST>     Annotations to draw over the frames, a comma-separated list of roots, critical, axes,
+                          circle and viewport, or all
+    --stats               Write the time spent in each stage of each frame to output/stats/<frame>.json
+    -h, --help            Print this message
+";
+
+/// The subset of the frames of an animation that should be rendered
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameSelection {
+    pub range: Option<Range<usize>>,
+    /// `(k, n)`: only render frames whose number is `k - 1` modulo `n`
+    pub shard: Option<(usize, usize)>,
+}
+
+impl FrameSelection {
+    /// Returns the selected frames, out of an animation of `total` frames
+    pub fn frames(&self, total: usize) -> Vec<usize> {
+        let range = match &self.range {
+            Some(range) => range.start.min(total)..range.end.min(total),
+            None => 0..total,
+        };
+
+        range.filter(|frame| match self.shard {
+            Some((k, n)) => frame % n == k - 1,
+            None => true,
+        }).collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Animate,
+    /// Render the scene file at the given path
+    Render(String),
+    /// Open the viewer, optionally on a scene file
+    View(Option<String>),
+    /// Analyze the critical points of the animation, or of a scene file
+    Analyze(Option<String>),
+    /// Estimate the fractal dimension of the boundary of the animation, or of a scene file
+    Dimension(Option<String>),
+    /// Print the orbit from a point, on a frame of the animation or on a scene file
+    Trace { start: Complex<f64>, scene: Option<String> },
+    /// Generate, score and write this many random scenes
+    Explore(usize),
+    /// Render a grid of thumbnails with one or two parameters changing, on a frame of the animation or a scene file
+    Sweep { columns: Axis, rows: Option<Axis>, scene: Option<String> },
+}
+
+#[derive(Clone, Debug)]
+pub struct Args {
+    pub command: Command,
+    pub target: Target,
+    pub frames: FrameSelection,
+    /// Do not skip frames whose image already exists
+    pub force: bool,
+    pub playback: Playback,
+    pub method: Method,
+    /// Compare the output of `method` with the brute-force pass
+    pub verify: bool,
+    /// Compare the coefficient and root forms of the Newton step
+    pub compare_forms: bool,
+    /// Export the raw layers alongside the image, see `export.rs`
+    pub raw: bool,
+    /// Where to write the statistics of the basins, see `basins.rs`
+    pub basin_stats: Option<String>,
+    /// Number of levels below the pixel for the box-counting dimension
+    pub refine: usize,
+    /// Seed of the random scenes of `explore`
+    pub seed: u64,
+    /// Draw the traced orbit over the rendered frame
+    pub overlay: bool,
+    pub overlays: Vec<Overlay>,
+    /// Write the per-stage timings of each frame as JSON
+    pub stats: bool,
+}
+
+impl Args {
+    /// Parses the command-line arguments of the process, printing the usage and exiting on error
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(Some(args)) => args,
+            Ok(None) => {
+                print!("{}", USAGE);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Error: {}\n\n{}", e, USAGE);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Returns `Ok(None)` if the help message was requested
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut output: Option<String> = None;
+        let mut format: Option<OutputFormat> = None;
+        let mut pipe: Option<String> = None;
+        let mut playback = Playback::default();
+        let mut method = Method::Brute;
+        let mut verify = false;
+        let mut compare_forms = false;
+        let mut frames = FrameSelection::default();
+        let mut force = false;
+        let mut raw = false;
+        let mut basin_stats = None;
+        let mut refine = 0;
+        let mut seed = 0;
+        let mut overlay = false;
+        let mut overlays = Vec::new();
+        let mut stats = false;
+
+        let mut args = args.into_iter().peekable();
+        let command = match args.peek().map(|arg| arg.as_str()) {
+            Some("animate") => {
+                args.next();
+                Command::Animate
+            }
+            Some("render") => {
+                args.next();
+                Command::Render(args.next().ok_or_else(|| String::from("missing scene file for 'render'"))?)
+            }
+            Some("view") => {
+                args.next();
+                Command::View(args.next_if(|arg| !arg.starts_with('-')))
+            }
+            Some("analyze") => {
+                args.next();
+                Command::Analyze(args.next_if(|arg| !arg.starts_with('-')))
+            }
+            Some("dimension") => {
+                args.next();
+                Command::Dimension(args.next_if(|arg| !arg.starts_with('-')))
+            }
+            Some("trace") => {
+                args.next();
+                let start = args.next().ok_or_else(|| String::from("missing start point for 'trace'"))?;
+                Command::Trace {
+                    start: parse_complex(&start).map_err(|e| format!("invalid start point: {}", e))?,
+                    scene: args.next_if(|arg| !arg.starts_with('-')),
+                }
+            }
+            Some("explore") => {
+                args.next();
+                let count = match args.next_if(|arg| !arg.starts_with('-')) {
+                    Some(count) => count.parse().map_err(|_| format!("invalid number of scenes '{}'", count))?,
+                    None => 24,
+                };
+                if count == 0 {
+                    return Err(String::from("explore needs at least one scene"));
+                }
+                Command::Explore(count)
+            }
+            Some("sweep") => {
+                args.next();
+                let axis = |spec: String| Axis::parse(&spec).map_err(|e| format!("invalid axis: {}", e));
+                Command::Sweep {
+                    columns: axis(args.next().ok_or_else(|| String::from("missing axis for 'sweep'"))?)?,
+                    rows: args.next_if(|arg| !arg.starts_with('-') && arg.contains('=')).map(axis).transpose()?,
+                    scene: args.next_if(|arg| !arg.starts_with('-')),
+                }
+            }
+            _ => Command::Animate,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    let range = value(&arg, args.next())?;
+                    frames.range = Some(parse_range(&range).ok_or_else(|| format!("invalid frame range '{}'", range))?);
+                }
+                "--shard" => {
+                    let shard = value(&arg, args.next())?;
+                    frames.shard = Some(parse_shard(&shard).ok_or_else(|| format!("invalid shard '{}', expected K/N with 1 <= K <= N", shard))?);
+                }
+                "--force" => force = true,
+                "-o" | "--output" => output = Some(value(&arg, args.next())?),
+                "--format" => {
+                    let name = value(&arg, args.next())?;
+                    format = Some(OutputFormat::from_name(&name).ok_or_else(|| {
+                        format!("unknown format '{}', expected one of {}", name, OutputFormat::NAMES)
+                    })?);
+                }
+                "--pipe" => pipe = Some(value(&arg, args.next())?),
+                "--fps" => {
+                    playback.fps = number(&arg, args.next())?;
+                    if playback.fps == 0 {
+                        return Err(String::from("--fps must be positive"));
+                    }
+                }
+                "--loop" => playback.loops = number(&arg, args.next())?,
+                "--method" => {
+                    let name = value(&arg, args.next())?;
+                    method = Method::from_name(&name).ok_or_else(|| {
+                        format!("unknown method '{}', expected one of {}", name, Method::NAMES)
+                    })?;
+                }
+                "--verify" => verify = true,
+                "--compare-forms" => compare_forms = true,
+                "--raw" => raw = true,
+                "--basin-stats" => {
+                    let path = value(&arg, args.next())?;
+                    // One file per frame, so that resumed animations and shards do not overwrite each other
+                    if !path.contains("{}") {
+                        return Err(format!("--basin-stats needs {{}} in '{}' for the frame number", path));
+                    }
+                    basin_stats = Some(path);
+                }
+                "--refine" => refine = number(&arg, args.next())?,
+                "--seed" => seed = number(&arg, args.next())?,
+                "--overlay" => overlay = true,
+                "--overlays" => {
+                    let list = value(&arg, args.next())?;
+                    overlays = Overlay::parse_list(&list).ok_or_else(|| {
+                        format!("invalid overlays '{}', expected a list of {}", list, Overlay::NAMES)
+                    })?;
+                }
+                "--stats" => stats = true,
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        let target = match (pipe, output) {
+            (Some(_), Some(_)) => return Err(String::from("--pipe and --output cannot be used together")),
+            (Some(command), None) => Target::Pipe(command),
+            (None, output) => {
+                let extension = output.as_deref()
+                    .and_then(|output| std::path::Path::new(output).extension())
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.to_ascii_lowercase());
+
+                match (extension.as_deref(), output) {
+                    (Some("gif"), Some(output)) => Target::Gif(output),
+                    // APNG frame delays are a fraction with a 16-bit denominator
+                    (Some("apng"), Some(_)) if playback.fps > u16::MAX as u32 => {
+                        return Err(format!("--fps must be at most {} for APNG", u16::MAX));
+                    }
+                    (Some("apng"), Some(output)) => Target::Apng(output),
+                    (Some("y4m"), Some(output)) => Target::Y4m(output),
+                    (_, Some(template)) => Target::Images {
+                        format: match format {
+                            Some(format) => format,
+                            None => OutputFormat::from_path(&template).ok_or_else(|| {
+                                format!("cannot guess the format of '{}', use --format", template)
+                            })?,
+                        },
+                        template,
+                    },
+                    (_, None) => {
+                        let format = format.unwrap_or(OutputFormat::Png8);
+                        // Auxiliary images must not overwrite the frames of the animation
+                        let prefix = match command {
+                            Command::Trace { .. } => "trace-",
+                            Command::Sweep { .. } => "sweep-",
+                            _ => "",
+                        };
+                        Target::Images {
+                            template: format!("output/{}{{}}.{}", prefix, format.extension()),
+                            format,
+                        }
+                    }
+                }
+            }
+        };
+
+        if format.is_some() && !matches!(target, Target::Images { .. }) {
+            return Err(String::from("--format only applies to image sequences"));
+        }
+
+        Ok(Some(Self {
+            command,
+            target,
+            frames,
+            force,
+            playback,
+            method,
+            verify,
+            compare_forms,
+            raw,
+            basin_stats,
+            refine,
+            seed,
+            overlay,
+            overlays,
+            stats,
+        }))
+    }
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("missing value for '{}'", arg))
+}
+
+fn number<T: std::str::FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+    let value = self::value(arg, value)?;
+    value.parse().map_err(|_| format!("invalid value '{}' for '{}'", value, arg))
+}
+
+/// Parses `a..b`, `a..`, `..b` or `a`
+fn parse_range(range: &str) -> Option<Range<usize>> {
+    match range.split_once("..") {
+        Some((start, end)) => {
+            let start = if start.is_empty() { 0 } else { start.parse().ok()? };
+            let end = if end.is_empty() { usize::MAX } else { end.parse().ok()? };
+            Some(start..end)
+        }
+        None => {
+            let frame: usize = range.parse().ok()?;
+            Some(frame..frame.checked_add(1)?)
+        }
+    }
+}
+
+/// Parses `k/n`
+fn parse_shard(shard: &str) -> Option<(usize, usize)> {
+    let (k, n) = shard.split_once('/')?;
+    let (k, n) = (k.parse().ok()?, n.parse().ok()?);
+    if k >= 1 && k <= n {
+        Some((k, n))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_frame_selection() {
+    let selection = |range, shard| FrameSelection { range, shard };
+
+    assert_eq!(selection(None, None).frames(4), vec![0, 1, 2, 3]);
+    assert_eq!(selection(parse_range("2..6"), None).frames(10), vec![2, 3, 4, 5]);
+    assert_eq!(selection(parse_range("8.."), None).frames(10), vec![8, 9]);
+    assert_eq!(selection(parse_range("..2"), None).frames(10), vec![0, 1]);
+    assert_eq!(selection(parse_range("12"), None).frames(10), vec![]);
+    assert_eq!(selection(None, parse_shard("2/3")).frames(8), vec![1, 4, 7]);
+    assert_eq!(selection(parse_range("3..9"), parse_shard("1/2")).frames(10), vec![4, 6, 8]);
+
+    assert_eq!(parse_range("1..a"), None);
+    assert_eq!(parse_range("18446744073709551615"), None);
+    assert_eq!(parse_shard("0/3"), None);
+    assert_eq!(parse_shard("4/3"), None);
+    assert_eq!(parse_shard("3"), None);
+}
+
+#[test]
+fn test_parse_args() {
+    let parse = |args: &[&str]| Args::parse(args.iter().map(|s| s.to_string()));
+
+    let args = parse(&[]).unwrap().unwrap();
+    assert_eq!(args.target, Target::Images {
+        template: String::from("output/{}.png"),
+        format: OutputFormat::Png8,
+    });
+    assert_eq!(args.playback, Playback::default());
+    assert_eq!(args.method, Method::Brute);
+
+    let args = parse(&["--format", "png16"]).unwrap().unwrap();
+    assert_eq!(args.target, Target::Images {
+        template: String::from("output/{}.png"),
+        format: OutputFormat::Png16,
+    });
+
+    let args = parse(&["-o", "render/frame-{}.exr", "--raw"]).unwrap().unwrap();
+    assert_eq!(args.target, Target::Images {
+        template: String::from("render/frame-{}.exr"),
+        format: OutputFormat::Exr,
+    });
+    assert!(args.raw);
+    assert_eq!(args.basin_stats, None);
+    let args = parse(&["--basin-stats", "basins/{}.csv"]).unwrap().unwrap();
+    assert_eq!(args.basin_stats, Some(String::from("basins/{}.csv")));
+    assert!(parse(&["--basin-stats", "basins.csv"]).is_err());
+
+    let args = parse(&["-o", "render/anim.GIF", "--fps", "24", "--loop", "2"]).unwrap().unwrap();
+    assert_eq!(args.target, Target::Gif(String::from("render/anim.GIF")));
+    assert_eq!(args.playback, Playback { fps: 24, loops: 2 });
+
+    let args = parse(&["render", "scene.txt", "-o", "out.png"]).unwrap().unwrap();
+    assert_eq!(args.command, Command::Render(String::from("scene.txt")));
+    assert_eq!(parse(&["view"]).unwrap().unwrap().command, Command::View(None));
+    assert_eq!(parse(&["view", "a.txt"]).unwrap().unwrap().command, Command::View(Some(String::from("a.txt"))));
+    let args = parse(&["analyze", "--frames", "3..5"]).unwrap().unwrap();
+    assert_eq!(args.command, Command::Analyze(None));
+    assert_eq!(args.frames.frames(10), vec![3, 4]);
+    let args = parse(&["dimension", "a.txt", "--refine", "3"]).unwrap().unwrap();
+    assert_eq!((args.command, args.refine), (Command::Dimension(Some(String::from("a.txt"))), 3));
+    let args = parse(&["trace", "0.5-2i", "--frames", "4", "--overlay"]).unwrap().unwrap();
+    assert_eq!(args.command, Command::Trace { start: Complex::new(0.5, -2.0), scene: None });
+    assert!(args.overlay);
+    assert_eq!(args.target, Target::Images { template: String::from("output/trace-{}.png"), format: OutputFormat::Png8 });
+    assert!(parse(&["trace", "a.txt"]).is_err());
+    let args = parse(&["explore", "12", "--seed", "7"]).unwrap().unwrap();
+    assert_eq!((args.command, args.seed), (Command::Explore(12), 7));
+    assert_eq!(parse(&["explore"]).unwrap().unwrap().command, Command::Explore(24));
+    assert!(parse(&["explore", "0"]).is_err());
+    let args = parse(&["sweep", "relaxation=1..2/3", "a.txt", "-o", "sweep.png"]).unwrap().unwrap();
+    assert_eq!(args.command, Command::Sweep {
+        columns: Axis::parse("relaxation=1..2/3").unwrap(),
+        rows: None,
+        scene: Some(String::from("a.txt")),
+    });
+    let args = parse(&["sweep", "epsilon=0.01,0.1", "scale=1,2"]).unwrap().unwrap();
+    assert!(matches!(args.command, Command::Sweep { rows: Some(_), scene: None, .. }));
+    assert_eq!(args.target, Target::Images { template: String::from("output/sweep-{}.png"), format: OutputFormat::Png8 });
+    assert!(parse(&["sweep", "a.txt"]).is_err());
+    assert_eq!(parse(&["--overlays", "roots,circle"]).unwrap().unwrap().overlays, vec![Overlay::Roots, Overlay::UnitCircle]);
+    assert!(parse(&["--overlays", "grid"]).is_err());
+
+    let args = parse(&["animate", "--frames", "100..200", "--shard", "3/8", "--force"]).unwrap().unwrap();
+    assert_eq!(args.command, Command::Animate);
+    assert_eq!(args.frames, FrameSelection { range: Some(100..200), shard: Some((3, 8)) });
+    assert!(args.force);
+
+    let args = parse(&["--pipe", "ffmpeg -i - out.mp4"]).unwrap().unwrap();
+    assert_eq!(args.target, Target::Pipe(String::from("ffmpeg -i - out.mp4")));
+
+    assert!(parse(&["--help"]).unwrap().is_none());
+    assert!(parse(&["-o", "frame.xyz"]).is_err());
+    assert!(parse(&["-o", "anim.y4m", "--format", "png"]).is_err());
+    assert!(parse(&["--pipe", "cat", "-o", "a.png"]).is_err());
+    assert!(parse(&["--fps", "0"]).is_err());
+    assert!(parse(&["-o", "anim.apng", "--fps", "65536"]).is_err());
+    assert_eq!(parse(&["-o", "anim.apng", "--fps", "65535"]).unwrap().unwrap().playback.fps, 65535);
+    assert_eq!(parse(&["-o", "anim.y4m", "--fps", "100000"]).unwrap().unwrap().playback.fps, 100000);
+    assert!(parse(&["--format"]).is_err());
+    assert!(parse(&["render"]).is_err());
+    assert_eq!(parse(&["--method", "progressive"]).unwrap().unwrap().method, Method::Progressive);
+    assert!(parse(&["--method", "fast"]).is_err());
+    let args = parse(&["--method", "subdivide", "--verify"]).unwrap().unwrap();
+    assert_eq!(args.method, Method::Subdivide);
+    assert!(args.verify);
+    assert!(parse(&["--compare-forms"]).unwrap().unwrap().compare_forms);
+}
@@ -0,0 +1,199 @@
+//! Critical-point analysis of the relaxed Newton map `N(z) = z - A step(z)`, with the step the scene renders with.
+//!
+//! Every attracting cycle of a rational map attracts at least one of its critical points, so iterating them finds
+//! all the cycles: when a critical point falls into a cycle instead of converging to a root, so does an open set
+//! of starting points, which shows up as a large unconverged region of the image.
+//!
+//! With the step `m f / f'` of multiplicity `m`, `N'(z) = 1 - A m + A m f f'' / f'^2`, so the critical points are the
+//! roots of `(1 - A m) f'^2 + A m f f''`, which is `f f''` for the plain step and `A = 1`; the modified step uses the
+//! multiplicity of the root `z` is close to, so each multiplicity gives the critical points around its roots. With the
+//! reformulated step `f f' / (f'^2 - f f'')`, they are the roots of
+//! `(1 + A) (f'^2 - f f'')^2 - A f' (f^2 f''' - 3 f f' f'' + 2 f'^3)`. The repeated roots of `f` are factored out of
+//! these polynomials, of which they are multiple roots too.
+
+use num::complex::Complex;
+use super::{MultiplicityMode, PolyInfo, Polynomial};
+use super::newton::{find_color, nearest_multiplicity, newton_step_with_derivative};
+
+/// Longest cycle that is looked for
+const MAX_PERIOD: usize = 64;
+/// Relative distance under which two points of an orbit are considered equal
+const CYCLE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fate {
+    /// Converges to the root with this index
+    Root(usize),
+    /// Falls into an attracting cycle through `point`, which is not a root; `multiplier` is `|(N^period)'|` on it
+    Cycle { period: usize, point: Complex<f64>, multiplier: f64 },
+    /// Neither converged nor settled into a short cycle within the iterations, or hit a pole of the map
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CriticalPoint {
+    pub z: Complex<f64>,
+    pub fate: Fate,
+}
+
+/// The critical points of the relaxed Newton map, leaving out those within `epsilon` of a root, which converge to it
+pub fn critical_points(info: &PolyInfo) -> Vec<Complex<f64>> {
+    let a = Complex::new(info.relaxation, 0.0);
+    let (f, df, ddf) = (&info.f, &info.df, &info.ddf);
+    // The repeated roots, each with its count minus one
+    let repeated = info.distinct_roots.iter()
+        .flat_map(|root| std::iter::repeat_n(root.value, root.count - 1))
+        .collect::<Vec<_>>();
+    let repeated = Polynomial::from_roots(&repeated);
+    let not_near_root = |z: &Complex<f64>| info.roots.iter().all(|root| (z - root).norm() >= info.epsilon);
+
+    if info.multiplicity_mode == MultiplicityMode::Reformulated {
+        let dddf = ddf.diff();
+        let s2 = &(df * df) - &(f * ddf);
+        let s3 = &(&(&(f * f) * &dddf) - &(&(&(f * df) * ddf) * Complex::new(3.0, 0.0)))
+            + &(&(&(df * df) * df) * Complex::new(2.0, 0.0));
+        let p = &(&(&s2 * &s2) * (1.0 + a)) - &(&(df * &s3) * a);
+        let p = p.div_rem(&repeated.pow(4)).0;
+        return p.roots().into_iter()
+            .map(|z| polish(z, None, info))
+            .filter(not_near_root)
+            .collect();
+    }
+
+    let mut multiplicities = info.distinct_roots.iter()
+        .map(|root| nearest_multiplicity(root.value, info.multiplicity_mode, info))
+        .collect::<Vec<_>>();
+    multiplicities.sort_by(f64::total_cmp);
+    multiplicities.dedup();
+    multiplicities.into_iter()
+        .flat_map(|m| {
+            let p = &(&(df * df) * (1.0 - a * m)) + &(&(f * ddf) * (a * m));
+            let p = p.div_rem(&repeated.pow(2)).0;
+            p.roots().into_iter()
+                .map(move |z| polish(z, Some(m), info))
+                .filter(move |&z| nearest_multiplicity(z, info.multiplicity_mode, info) == m)
+        })
+        .filter(not_near_root)
+        .collect()
+}
+
+/// Refines a critical point with Newton's method on `S1^2 - A m S2` with the step of multiplicity `m`, and on
+/// `(1 + A) S2^2 - 2 A S1 S3` with the reformulated step, where `Sk = sum(1 / (z - r_i)^k)`: since `f' / f = S1`,
+/// `f'' / f = S1^2 - S2` and `S'k = -k S(k+1)`, it vanishes where `N'` does, and is computed from the roots, without
+/// the loss of precision of the coefficients when they are widely spread. It also vanishes at infinity, so a point
+/// that drifts away is left as it was.
+fn polish(start: Complex<f64>, multiplicity: Option<f64>, info: &PolyInfo) -> Complex<f64> {
+    let a = info.relaxation;
+    let mut z = start;
+    for _ in 0..50 {
+        let zero = Complex::new(0.0, 0.0);
+        let (mut s1, mut s2, mut s3, mut s4) = (zero, zero, zero, zero);
+        for root in info.roots.iter() {
+            let inverse = 1.0 / (z - root);
+            s1 += inverse;
+            s2 += inverse * inverse;
+            s3 += inverse * inverse * inverse;
+            s4 += inverse * inverse * inverse * inverse;
+        }
+        let step = match multiplicity {
+            Some(m) => (s1 * s1 - a * m * s2) / (2.0 * a * m * s3 - 2.0 * s1 * s2),
+            None => ((1.0 + a) * s2 * s2 - 2.0 * a * s1 * s3) / (6.0 * a * s1 * s4 - (4.0 + 2.0 * a) * s2 * s3),
+        };
+        if !step.is_finite() {
+            break;
+        }
+        z -= step;
+        if step.norm() < 1e-15 * z.norm().max(1.0) {
+            break;
+        }
+    }
+
+    if (z - start).norm() < 1e-3 * start.norm().max(1.0) {
+        z
+    } else {
+        start
+    }
+}
+
+/// Iterates each critical point of the relaxed Newton map and reports where it goes
+pub fn analyze(info: &PolyInfo) -> Vec<CriticalPoint> {
+    critical_points(info).into_iter()
+        .map(|z| CriticalPoint { z, fate: fate(z, info) })
+        .collect()
+}
+
+fn fate(mut z: Complex<f64>, info: &PolyInfo) -> Fate {
+    let step = |z: Complex<f64>| {
+        let (step, derivative) = newton_step_with_derivative(z, info);
+        (z - step * info.relaxation, derivative)
+    };
+
+    for _ in 0..info.iterations {
+        let root = find_color(z, info);
+        if root < info.roots.len() {
+            return Fate::Root(root);
+        }
+        z = step(z).0;
+        if !z.is_finite() {
+            return Fate::Unknown;
+        }
+    }
+
+    // After that many iterations, an orbit attracted by a cycle is on it up to rounding
+    let (start, mut multiplier) = (z, 1.0);
+    for period in 1..=MAX_PERIOD {
+        let (next, derivative) = step(z);
+        multiplier *= derivative.norm();
+        z = next;
+        if (z - start).norm() < CYCLE_TOLERANCE * start.norm().max(1.0) {
+            return if multiplier < 1.0 {
+                Fate::Cycle { period, point: start, multiplier }
+            } else {
+                Fate::Unknown
+            };
+        }
+    }
+    Fate::Unknown
+}
+
+#[test]
+fn test_critical_points() {
+    use super::Polynomial;
+    use super::scene::Scene;
+
+    let info = |roots: Vec<Complex<f64>>| PolyInfo::new(&Scene { roots, relaxation: 1.0, ..Scene::default() });
+
+    // The Newton map of z^3 - 2z + 2 has the attracting cycle 0 -> 1 -> 0, which catches the critical point 0
+    let f = Polynomial::new(&[Complex::new(2.0, 0.0), Complex::new(-2.0, 0.0), Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)]);
+    let critical = analyze(&info(f.roots()));
+    assert_eq!(critical.len(), 1);
+    assert!(critical[0].z.norm() < 1e-9);
+    assert!(matches!(critical[0].fate, Fate::Cycle { period: 2, multiplier, .. } if multiplier < 1e-6));
+
+    // Moving a root of z^3 - 1 off the unit circle leaves a single free critical point, which converges
+    let unity = (0..3).map(|k| Complex::from_polar(1.0, k as f64 * 2.0 * std::f64::consts::PI / 3.0)).collect::<Vec<_>>();
+    let critical = analyze(&info(vec![unity[0] * 2.0, unity[1], unity[2]]));
+    assert_eq!(critical.len(), 1);
+    assert_eq!(critical[0].fate, Fate::Root(0));
+}
+
+#[test]
+fn test_critical_points_modes() {
+    use super::scene::Scene;
+
+    // The critical points follow the Newton step of each mode, which differ around the double root 1
+    let roots = vec![Complex::new(1.0, 0.0), Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0), Complex::new(0.0, 2.0)];
+    let info = |multiplicity_mode| {
+        PolyInfo::new(&Scene { roots: roots.clone(), relaxation: 0.7, multiplicity_mode, ..Scene::default() })
+    };
+    let plain = critical_points(&info(MultiplicityMode::Plain));
+    for mode in [MultiplicityMode::Plain, MultiplicityMode::Modified, MultiplicityMode::Reformulated] {
+        let info = info(mode);
+        let critical = critical_points(&info);
+        assert!(!critical.is_empty());
+        assert!(critical.iter().all(|&z| newton_step_with_derivative(z, &info).1.norm() < 1e-9));
+        if mode != MultiplicityMode::Plain {
+            assert!(critical.iter().any(|z| plain.iter().all(|p| (z - p).norm() > 1e-3)));
+        }
+    }
+}
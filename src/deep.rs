@@ -0,0 +1,119 @@
+//! Arbitrary-precision backend for deep zooms, behind the `deep` feature.
+//!
+//! Each pixel starts at `center + offset`, where the center is read from the digits of the scene file
+//! and the small offset is computed in `f64`. The iteration runs with MPFR floats only as long as needed:
+//! the derivative of `z` with respect to the starting point is tracked alongside it, and once the area covered
+//! by a pixel has grown large enough for `f64` to tell neighboring pixels apart, the iteration goes on in `f64`.
+//! The polynomial is always evaluated from its coefficients here, whatever the `form` of the scene, with the Newton
+//! step of its `multiplicity` mode; the `f64` iteration that follows uses both.
+
+use num::complex::Complex;
+use rug::{Complex as BigComplex, Float};
+use super::{MultiplicityMode, PolyInfo, Polynomial};
+use super::newton::{
+    nearest_multiplicity, newton_step_with_derivative, track_newton_raphson, Arrival, Sample, F64_FOOTPRINT,
+};
+use super::scene::Scene;
+
+pub struct DeepInfo {
+    /// Bits of mantissa of the floats
+    precision: u32,
+    center: BigComplex,
+    f: Vec<BigComplex>,
+    df: Vec<BigComplex>,
+    ddf: Vec<BigComplex>,
+}
+
+impl DeepInfo {
+    pub fn new(scene: &Scene, f: &Polynomial, precision: u32) -> Self {
+        let center = match &scene.center_digits {
+            Some((re, im)) => {
+                let parse = |digits: &str| {
+                    Float::with_val(precision, Float::parse(digits).expect("the center is checked by `Scene::parse`"))
+                };
+                BigComplex::with_val(precision, (parse(re), parse(im)))
+            }
+            None => BigComplex::with_val(precision, (scene.center.re, scene.center.im)),
+        };
+        let coefficients = |p: &Polynomial| p.coefficients().iter()
+            .map(|c| BigComplex::with_val(precision, (c.re, c.im)))
+            .collect::<Vec<_>>();
+        let df = f.diff();
+
+        Self {
+            precision,
+            center,
+            f: coefficients(f),
+            df: coefficients(&df),
+            ddf: coefficients(&df.diff()),
+        }
+    }
+
+    /// Evaluates the polynomial with the given coefficients at `z`, using Horner's method
+    fn eval(&self, coefficients: &[BigComplex], z: &BigComplex) -> BigComplex {
+        let mut res = BigComplex::with_val(self.precision, (0.0, 0.0));
+        for c in coefficients.iter().rev() {
+            res *= z;
+            res += c;
+        }
+        res
+    }
+
+    /// Same as `newton::newton_step`, from the coefficients
+    fn newton_step(&self, z: &BigComplex, info: &PolyInfo) -> BigComplex {
+        let f = self.eval(&self.f, z);
+        let df = self.eval(&self.df, z);
+        if info.multiplicity_mode == MultiplicityMode::Reformulated {
+            let ddf = self.eval(&self.ddf, z);
+            let mut denominator = BigComplex::with_val(self.precision, &df * &df);
+            denominator -= &BigComplex::with_val(self.precision, &f * &ddf);
+            let mut step = BigComplex::with_val(self.precision, &f * &df);
+            step /= &denominator;
+            step
+        } else {
+            let mut step = BigComplex::with_val(self.precision, &f / &df);
+            step *= nearest_multiplicity(to_f64(z), info.multiplicity_mode, info);
+            step
+        }
+    }
+
+    /// Same as `newton::calc_pixel`
+    pub fn calc_pixel(&self, x: usize, y: usize, info: &PolyInfo, scene: &Scene) -> Sample {
+        let offset = scene.pixel_offset(x as f64, y as f64);
+        let mut z = BigComplex::with_val(self.precision, &self.center);
+        z += BigComplex::with_val(self.precision, (offset.re, offset.im));
+
+        // |dz/dc| times the distance between two pixels
+        let mut footprint = scene.pixel_size();
+        let mut arrival = None;
+
+        for n in 0..info.iterations {
+            let mut step = self.newton_step(&z, info);
+            step *= info.relaxation;
+            if arrival.is_none() && to_f64(&step).norm() < info.epsilon {
+                arrival = Some(Arrival { z: to_f64(&z), derivative: footprint / scene.pixel_size(), steps: n });
+            }
+
+            footprint *= newton_step_with_derivative(to_f64(&z), info).1.norm();
+            z -= &step;
+
+            let c = to_f64(&z);
+            if footprint > F64_FOOTPRINT * c.norm().max(1.0) {
+                let (c, iterations, tracked) = track_newton_raphson(c, n + 1, footprint / scene.pixel_size(), info);
+                return Sample::new(c, iterations, arrival.unwrap_or(tracked), info);
+            }
+            if n % 10 == 0 && info.roots.iter().any(|root| (c - root).norm() < info.epsilon) {
+                let last = Arrival { z: c, derivative: footprint / scene.pixel_size(), steps: n + 1 };
+                return Sample::new(c, n + 1, arrival.unwrap_or(last), info);
+            }
+        }
+
+        let z = to_f64(&z);
+        let last = Arrival { z, derivative: footprint / scene.pixel_size(), steps: info.iterations };
+        Sample::new(z, info.iterations, arrival.unwrap_or(last), info)
+    }
+}
+
+fn to_f64(z: &BigComplex) -> Complex<f64> {
+    Complex::new(z.real().to_f64(), z.imag().to_f64())
+}
@@ -0,0 +1,220 @@
+//! Box-counting estimate of the fractal dimension of the basin boundary, the Julia set of the Newton map.
+//!
+//! The image is covered with square boxes of 1, 2, 4, ... pixels, whose corners are pixel centers, and the boxes
+//! crossed by the boundary, those holding pixels of different roots, are counted; the dimension is the slope of
+//! `ln N(s)` against `ln(1 / s)`, fitted by least squares.
+//!
+//! Refining goes below the pixel: each crossed box of 1 pixel is split into 2x2, 4x4, ... cells, the Newton-Raphson
+//! method is run again on their corners, and the cells whose corners do not all converge to the same root are counted,
+//! like the boxes above. This follows the boundary at finer scales without rendering the whole image. Refinement
+//! always runs in `f64`.
+
+use std::sync::Mutex;
+use scoped_threadpool::Pool;
+use super::{PolyInfo, THREADS};
+use super::newton::{find_color, newton_raphson};
+use super::scene::Scene;
+
+/// Boxes must fit at least this many times in the shortest side of the image
+const MIN_BOXES: usize = 4;
+
+/// The number of boxes of side `size` pixels that hold part of the boundary
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoxCount {
+    pub size: f64,
+    pub count: usize,
+}
+
+/// A least-squares fit of `ln N(s) = dimension * ln(1 / s) + intercept`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub dimension: f64,
+    pub intercept: f64,
+    /// Standard error of the dimension
+    pub standard_error: f64,
+    pub r_squared: f64,
+    /// Number of scales the fit is over
+    pub scales: usize,
+}
+
+impl Estimate {
+    /// Fits the counts, which must span at least three scales; scales without any box are left out
+    pub fn fit(counts: &[BoxCount]) -> Option<Self> {
+        let points = counts.iter()
+            .filter(|count| count.count > 0)
+            .map(|count| ((1.0 / count.size).ln(), (count.count as f64).ln()))
+            .collect::<Vec<_>>();
+        let n = points.len() as f64;
+        if points.len() < 3 {
+            return None;
+        }
+
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum::<f64>();
+        let sxy = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum::<f64>();
+        let syy = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum::<f64>();
+
+        let dimension = sxy / sxx;
+        let intercept = mean_y - dimension * mean_x;
+        let residuals = points.iter().map(|p| (p.1 - intercept - dimension * p.0).powi(2)).sum::<f64>();
+
+        Some(Self {
+            dimension,
+            intercept,
+            standard_error: (residuals / (n - 2.0) / sxx).sqrt(),
+            r_squared: if syy > 0.0 { 1.0 - residuals / syy } else { 1.0 },
+            scales: points.len(),
+        })
+    }
+
+    /// The 95% confidence interval of the dimension
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let margin = student_t(self.scales - 2) * self.standard_error;
+        (self.dimension - margin, self.dimension + margin)
+    }
+}
+
+/// The 97.5% quantile of Student's t-distribution with `df` degrees of freedom
+fn student_t(df: usize) -> f64 {
+    const TABLE: [f64; 10] = [12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228];
+    const Z: f64 = 1.959964;
+    match df {
+        1..=10 => TABLE[df - 1],
+        // Cornish-Fisher expansion, within 0.01 of the exact value from there on
+        _ => Z + (Z.powi(3) + Z) / (4.0 * df as f64),
+    }
+}
+
+/// Counts the boxes of 1, 2, 4, ... pixels crossed by the boundary
+pub fn box_counts(table: &[usize], width: usize, height: usize) -> Vec<BoxCount> {
+    // The box of 1 pixel at `(x, y)` has the centers of pixels `(x, y)` to `(x + 1, y + 1)` as corners
+    let (mut grid_width, mut grid_height) = (width.saturating_sub(1), height.saturating_sub(1));
+    let mut grid = (0..grid_width * grid_height).map(|i| crossed(i % grid_width, i / grid_width, table, width))
+        .collect::<Vec<_>>();
+    let mut size = 1;
+    let mut res = Vec::new();
+
+    while size * MIN_BOXES <= width.min(height) {
+        res.push(BoxCount { size: size as f64, count: grid.iter().filter(|&&edge| edge).count() });
+
+        // Each box of the next scale holds 2x2 boxes of this one, which share their sides, so it is crossed if
+        // any of them is
+        let (next_width, next_height) = (grid_width.div_ceil(2), grid_height.div_ceil(2));
+        let mut next = vec![false; next_width * next_height];
+        for y in 0..grid_height {
+            for x in 0..grid_width {
+                next[x / 2 + y / 2 * next_width] |= grid[x + y * grid_width];
+            }
+        }
+        (grid, grid_width, grid_height) = (next, next_width, next_height);
+        size *= 2;
+    }
+    res
+}
+
+/// Whether the box of 1 pixel whose top-left corner is the center of pixel `(x, y)` is crossed by the boundary
+fn crossed(x: usize, y: usize, table: &[usize], width: usize) -> bool {
+    let root = table[x + y * width];
+    table[x + 1 + y * width] != root || table[x + (y + 1) * width] != root || table[x + 1 + (y + 1) * width] != root
+}
+
+/// Counts the cells of 1/2, 1/4, ... pixels crossed by the boundary, inside the crossed boxes of 1 pixel, for `levels`
+/// levels
+pub fn refined_counts(table: &[usize], info: &PolyInfo, scene: &Scene, levels: usize) -> Vec<BoxCount> {
+    let (width, height) = (scene.width, scene.height);
+    let boxes_width = width.saturating_sub(1);
+    let edges = (0..boxes_width * height.saturating_sub(1))
+        .filter(|&i| crossed(i % boxes_width, i / boxes_width, table, width))
+        .map(|i| i % boxes_width + i / boxes_width * width)
+        .collect::<Vec<_>>();
+    let chunk = edges.len().div_ceil(THREADS as usize * 4).max(1);
+
+    (1..=levels).map(|level| {
+        let cells = 1 << level;
+        let count = Mutex::new(0);
+        let mut pool = Pool::new(THREADS);
+        pool.scoped(|scoped| {
+            for pixels in edges.chunks(chunk) {
+                let count = &count;
+                scoped.execute(move || {
+                    let crossed = pixels.iter().map(|&i| crossed_cells(i % width, i / width, cells, info, scene)).sum::<usize>();
+                    *count.lock().unwrap() += crossed;
+                });
+            }
+        });
+        BoxCount { size: 1.0 / cells as f64, count: count.into_inner().unwrap() }
+    }).collect()
+}
+
+/// Splits the box of 1 pixel at `(x, y)` into `cells` by `cells` cells, and counts those whose corners converge to
+/// different roots
+fn crossed_cells(x: usize, y: usize, cells: usize, info: &PolyInfo, scene: &Scene) -> usize {
+    let corners = (0..=cells).flat_map(|j| (0..=cells).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let c = scene.pixel_to_complex(x as f64 + i as f64 / cells as f64, y as f64 + j as f64 / cells as f64);
+            find_color(newton_raphson(c, info).0, info)
+        })
+        .collect::<Vec<_>>();
+
+    let corner = |i: usize, j: usize| corners[i + j * (cells + 1)];
+    (0..cells).flat_map(|j| (0..cells).map(move |i| (i, j)))
+        .filter(|&(i, j)| {
+            let root = corner(i, j);
+            corner(i + 1, j) != root || corner(i, j + 1) != root || corner(i + 1, j + 1) != root
+        })
+        .count()
+}
+
+#[test]
+fn test_box_counting() {
+    // A straight boundary has dimension 1; it is between columns 31 and 32, so at every scale it crosses one column
+    // of the boxes, 64 of 1 pixel high between the 65 rows of pixels
+    let (width, height) = (65, 65);
+    let table = (0..width * height).map(|i| (i % width < 32) as usize).collect::<Vec<_>>();
+    let counts = box_counts(&table, width, height);
+    assert_eq!(counts.iter().map(|count| count.size).collect::<Vec<_>>(), vec![1.0, 2.0, 4.0, 8.0, 16.0]);
+    assert_eq!(counts[0].count, 64);
+    assert_eq!(counts[4].count, 4);
+    let estimate = Estimate::fit(&counts).unwrap();
+    assert!((estimate.dimension - 1.0).abs() < 1e-12, "{:?}", estimate);
+    assert!((estimate.r_squared - 1.0).abs() < 1e-12);
+
+    // Counts following an exact power law
+    let exact = (0..6).map(|k| BoxCount { size: 0.5f64.powi(k), count: 3 * 3usize.pow(k as u32) }).collect::<Vec<_>>();
+    let estimate = Estimate::fit(&exact).unwrap();
+    assert!((estimate.dimension - 3f64.ln() / 2f64.ln()).abs() < 1e-12);
+    assert!(estimate.standard_error < 1e-9);
+    let (low, high) = estimate.confidence_interval();
+    assert!(low <= estimate.dimension && estimate.dimension <= high);
+    assert_eq!(Estimate::fit(&exact[..2]), None);
+    assert!((student_t(30) - 2.042).abs() < 0.01);
+}
+
+#[test]
+fn test_refined_counts() {
+    use num::complex::Complex;
+    // The basins of 1 and -1 are split by the imaginary axis, which falls between two columns of pixels
+    let scene = Scene {
+        width: 64,
+        height: 64,
+        center: Complex::new(0.01, 0.0),
+        scale: 2.0,
+        relaxation: 1.0,
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)],
+        ..Scene::default()
+    };
+    let info = PolyInfo::new(&scene);
+    let table = (0..scene.width * scene.height)
+        .map(|i| find_color(newton_raphson(scene.pixel_to_complex((i % scene.width) as f64, (i / scene.width) as f64), &info).0, &info))
+        .collect::<Vec<_>>();
+
+    let mut counts = box_counts(&table, scene.width, scene.height);
+    let coarse = Estimate::fit(&counts).unwrap();
+    let refined = refined_counts(&table, &info, &scene, 3);
+    assert_eq!(refined.iter().map(|count| count.count).collect::<Vec<_>>(), vec![2 * 63, 4 * 63, 8 * 63]);
+    counts.extend(refined);
+    let fine = Estimate::fit(&counts).unwrap();
+    assert!((coarse.dimension - 1.0).abs() < 0.01, "{:?}", coarse);
+    assert!((fine.dimension - coarse.dimension).abs() < 0.01, "{:?}", fine);
+}
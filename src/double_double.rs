@@ -0,0 +1,249 @@
+//! Double-double floats: an unevaluated sum of two `f64`s, giving about 106 bits of mantissa,
+//! for zooms too deep for `f64` but not deep enough to need arbitrary precision.
+//!
+//! The algorithms are those of the QD library (Hida, Li and Bailey). Only what the Newton-Raphson iteration needs
+//! is implemented; together with `num::complex::Complex`, this gives a complex double-double type.
+
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg};
+use num::complex::Complex;
+use num::traits::{Num, Zero, One};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+pub type ComplexDD = Complex<DoubleDouble>;
+
+/// Largest power of ten in the range of an `f64`, and so of a double-double
+const MAX_EXPONENT: u32 = 308;
+
+/// `a + b` and its rounding error
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// `a + b` and its rounding error, assuming `|a| >= |b|`
+#[inline]
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// `a * b` and its rounding error
+#[inline]
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub const fn new(x: f64) -> Self {
+        Self { hi: x, lo: 0.0 }
+    }
+
+    #[inline]
+    fn from_parts((hi, lo): (f64, f64)) -> Self {
+        Self { hi, lo }
+    }
+
+    #[inline]
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    pub fn trunc(self) -> Self {
+        let hi = self.hi.trunc();
+        if hi == self.hi {
+            Self::from_parts(quick_two_sum(hi, self.lo.trunc()))
+        } else {
+            Self::new(hi)
+        }
+    }
+
+    /// Parses a decimal number, like `-1.25e-3`, keeping all of the precision of a double-double.
+    /// Numbers that are out of its range, like `1e400`, are rejected.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (mantissa, exponent) = match value.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (value, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(mantissa) => (true, mantissa),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        // Trailing zeros do not change the value, but would lower the exponent
+        let fraction = fraction.trim_end_matches('0');
+
+        let ten = Self::new(10.0);
+        let mut res = Self::zero();
+        for c in integer.chars().chain(fraction.chars()) {
+            res = res * ten + Self::new(c.to_digit(10)? as f64);
+        }
+
+        let exponent = exponent.checked_sub(i32::try_from(fraction.len()).ok()?)?;
+        if exponent.unsigned_abs() > MAX_EXPONENT {
+            return None;
+        }
+        let power = ten.powu(exponent.unsigned_abs());
+        let res = if exponent >= 0 { res * power } else { res / power };
+        if !res.hi.is_finite() {
+            return None;
+        }
+        Some(if negative { -res } else { res })
+    }
+
+    /// `self^n`, by squaring
+    fn powu(self, mut n: u32) -> Self {
+        let (mut res, mut square) = (Self::one(), self);
+        while n > 0 {
+            if n & 1 == 1 {
+                res = res * square;
+            }
+            square = square * square;
+            n >>= 1;
+        }
+        res
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(x: f64) -> Self {
+        Self::new(x)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        let (s, e) = two_sum(self.hi, other.hi);
+        let (t, f) = two_sum(self.lo, other.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        Self::from_parts(quick_two_sum(s, e + f))
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        Self::from_parts(quick_two_sum(p, e + (self.hi * other.lo + self.lo * other.hi)))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, other: Self) -> Self {
+        let q1 = self.hi / other.hi;
+        let r = self - other * Self::new(q1);
+        let q2 = r.hi / other.hi;
+        let r = r - other * Self::new(q2);
+        let q3 = r.hi / other.hi;
+        Self::from_parts(quick_two_sum(q1, q2)) + Self::new(q3)
+    }
+}
+
+impl Rem for DoubleDouble {
+    type Output = Self;
+
+    fn rem(self, other: Self) -> Self {
+        self - other * (self / other).trunc()
+    }
+}
+
+impl Zero for DoubleDouble {
+    fn zero() -> Self {
+        Self::new(0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.hi == 0.0 && self.lo == 0.0
+    }
+}
+
+impl One for DoubleDouble {
+    fn one() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Num for DoubleDouble {
+    type FromStrRadixErr = ();
+
+    fn from_str_radix(value: &str, radix: u32) -> Result<Self, ()> {
+        if radix != 10 {
+            return Err(());
+        }
+        Self::parse(value).ok_or(())
+    }
+}
+
+pub fn to_complex_dd(z: Complex<f64>) -> ComplexDD {
+    Complex::new(z.re.into(), z.im.into())
+}
+
+pub fn to_complex_f64(z: ComplexDD) -> Complex<f64> {
+    Complex::new(z.re.to_f64(), z.im.to_f64())
+}
+
+#[test]
+fn test_double_double() {
+    let third = DoubleDouble::one() / DoubleDouble::new(3.0);
+    let error = third * DoubleDouble::new(3.0) - DoubleDouble::one();
+    assert!(error.to_f64().abs() < 1e-31);
+
+    // 0.1 cannot be written exactly in binary, the low part holds the error of the f64 approximation
+    let tenth = DoubleDouble::parse("0.1").unwrap();
+    assert_eq!(tenth.hi, 0.1);
+    assert!((tenth - DoubleDouble::one() / DoubleDouble::new(10.0)).to_f64().abs() < 1e-32);
+    assert!(tenth.lo != 0.0);
+
+    let x = DoubleDouble::parse("-1.0000000000000000000000000001e-3").unwrap();
+    let y = x + DoubleDouble::parse("1e-3").unwrap();
+    assert!((y.to_f64() + 1e-31).abs() < 1e-34);
+
+    assert_eq!(DoubleDouble::parse("2.5E2"), Some(DoubleDouble::new(250.0)));
+    assert_eq!(DoubleDouble::new(7.0) % DoubleDouble::new(2.0), DoubleDouble::new(1.0));
+    assert_eq!(DoubleDouble::parse("1.2.3"), None);
+    assert_eq!(DoubleDouble::parse("."), None);
+
+    // Huge exponents are rejected at once, instead of overflowing or looping
+    assert_eq!(DoubleDouble::parse("1.5e300").unwrap().hi, 1.5e300);
+    assert_eq!(DoubleDouble::parse("1.000000000000000000000000000000"), Some(DoubleDouble::one()));
+    assert_eq!(DoubleDouble::parse("0.12345678901234567890123e-2000000000"), None);
+    assert_eq!(DoubleDouble::parse("0.123e-2147483640"), None);
+    assert_eq!(DoubleDouble::parse("9e308"), None);
+    assert_eq!(DoubleDouble::parse("inf"), None);
+}
@@ -1,46 +1,49 @@
-use super::{WIDTH, HEIGHT};
+use super::render::{Layers, normalize, point_mul};
+use super::scene::Scene;
 use distance_transform::GenericGrid;
+use image::{ImageBuffer, Rgb, Rgb32FImage};
 use num::traits::FloatConst;
 
-pub fn is_edge(x: usize, y: usize, table: &[usize]) -> bool {
-    let color = table[x + y * WIDTH];
+/// Returns true if any of the 8 neighbors of `(x, y)` converged to a different root than it
+pub fn is_edge(x: usize, y: usize, table: &[usize], width: usize, height: usize) -> bool {
+    let color = table[x + y * width];
     if x > 0 {
-        if table[x - 1 + y * WIDTH] != color {
+        if table[x - 1 + y * width] != color {
             return true;
         }
         if y > 0 {
-            if table[x - 1 - WIDTH + y * WIDTH] != color {
+            if table[x - 1 + (y - 1) * width] != color {
                 return true;
             }
         }
-        if y < HEIGHT - 1 {
-            if table[x - 1 + WIDTH + y * WIDTH] != color {
+        if y < height - 1 {
+            if table[x - 1 + width + y * width] != color {
                 return true;
             }
         }
     }
-    if x < WIDTH - 1 {
-        if table[x + 1 + y * WIDTH] != color {
+    if x < width - 1 {
+        if table[x + 1 + y * width] != color {
             return true;
         }
         if y > 0 {
-            if table[x + 1 - WIDTH + y * WIDTH] != color {
+            if table[x + 1 + (y - 1) * width] != color {
                 return true;
             }
         }
-        if y < HEIGHT - 1 {
-            if table[x + 1 + WIDTH + y * WIDTH] != color {
+        if y < height - 1 {
+            if table[x + 1 + width + y * width] != color {
                 return true;
             }
         }
     }
     if y > 0 {
-        if table[x - WIDTH + y * WIDTH] != color {
+        if table[x + (y - 1) * width] != color {
             return true;
         }
     }
-    if y < HEIGHT - 1 {
-        if table[x + WIDTH + y * WIDTH] != color {
+    if y < height - 1 {
+        if table[x + width + y * width] != color {
             return true;
         }
     }
@@ -48,13 +51,18 @@ pub fn is_edge(x: usize, y: usize, table: &[usize]) -> bool {
     return false;
 }
 
-pub fn gaussian_blur(map: &mut GenericGrid<f64>, radius: u8) {
+/// Blurs `map` with a Gaussian kernel of standard deviation `radius` pixels, cut off at `radius` rounded up
+pub fn gaussian_blur(map: &mut GenericGrid<f64>, radius: f64) {
+    if radius <= 0.0 {
+        return;
+    }
+    let half_width = radius.ceil() as isize;
+
     // Compute kernel
-    let mut kernel = vec![0.0; (2 * radius + 1) as usize];
+    let mut kernel = vec![0.0; (2 * half_width + 1) as usize];
     let sqrt_2pi: f64 = (f64::PI() * 2.0).sqrt();
-    for dx in -(radius as isize)..=(radius as isize) {
-        let radius = radius as f64;
-        let index = (dx + radius as isize) as usize;
+    for dx in -half_width..=half_width {
+        let index = (dx + half_width) as usize;
         let dx = dx as f64;
         kernel[index] = (-dx * dx / 2.0 / radius / radius).exp() / sqrt_2pi / radius;
     }
@@ -64,8 +72,8 @@ pub fn gaussian_blur(map: &mut GenericGrid<f64>, radius: u8) {
     for (x, y, _c) in map.iter() {
         let mut sigma = 0.0;
         let mut acc = 0.0;
-        for dx in -(radius as isize)..=(radius as isize) {
-            let index = (dx + radius as isize) as usize;
+        for dx in -half_width..=half_width {
+            let index = (dx + half_width) as usize;
             if dx < 0 && x < (-dx) as usize || dx > 0 && x + dx as usize >= buffer.width() {
                 continue;
             }
@@ -79,8 +87,8 @@ pub fn gaussian_blur(map: &mut GenericGrid<f64>, radius: u8) {
     for (x, y, _c) in buffer.iter() {
         let mut sigma = 0.0;
         let mut acc = 0.0;
-        for dy in -(radius as isize)..=(radius as isize) {
-            let index = (dy + radius as isize) as usize;
+        for dy in -half_width..=half_width {
+            let index = (dy + half_width) as usize;
             if dy < 0 && y < (-dy) as usize || dy > 0 && y + dy as usize >= buffer.height() {
                 continue;
             }
@@ -90,3 +98,41 @@ pub fn gaussian_blur(map: &mut GenericGrid<f64>, radius: u8) {
         map.set(x, y, sigma / acc);
     }
 }
+
+/// The color of the basin of root `root` before shading, with components between 0 and 255
+pub fn basin_color(root: usize, scene: &Scene) -> (f64, f64, f64) {
+    if Some(root) == scene.background {
+        return (50.0, 50.0, 55.0);
+    }
+    let a = root as f64 / scene.roots.len() as f64 * 2.0 * f64::PI() - 0.5;
+    (250.0, (a.sin() + 1.0) / 2.0 * 160.0 + 70.0, (-a.cos() + 1.0) / 2.0 * 160.0 + 70.0)
+}
+
+/// Shades the layers of a frame, returning an image whose channels are in `[0, 1]` (sRGB)
+pub fn shade(layers: &Layers, scene: &Scene) -> Rgb32FImage {
+    let n_roots = scene.roots.len();
+    let bg_color_id = scene.background.unwrap_or(usize::MAX);
+    let shadow = normalize(scene.shadow);
+    let mut image = ImageBuffer::new(layers.width as u32, layers.height as u32);
+
+    for y in 0..layers.height {
+        for x in 0..layers.width {
+            let color = layers.table[x + y * layers.width];
+
+            if color == n_roots {
+                image.put_pixel(x as u32, y as u32, Rgb([0.0, 0.0, 0.0]));
+            } else {
+                let nabla = (*layers.nabla_dx.get(x, y).unwrap(), *layers.nabla_dy.get(x, y).unwrap());
+                let shadow_orient = if color == bg_color_id { 1.0 } else { 0.3 };
+                let s = 1.0 - (1.0 - *layers.proximity.get(x, y).unwrap()) * (1.0 - (shadow_orient * point_mul(nabla, shadow)).max(0.0) * scene.shadow_strength);
+
+                let (r, g, b) = basin_color(color, scene);
+                let s = if color == bg_color_id { s } else { 0.5 * s + 0.5 };
+                let (r, g, b) = (r * s, g * s, b * s);
+                image.put_pixel(x as u32, y as u32, Rgb([(r / 255.0) as f32, (g / 255.0) as f32, (b / 255.0) as f32]));
+            }
+        }
+    }
+
+    image
+}
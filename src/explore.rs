@@ -0,0 +1,174 @@
+//! Exploration: random root layouts, rendered as thumbnails and ranked by how interesting they look.
+//!
+//! Each candidate is drawn from a seeded `Rng`, so that `newton explore --seed S` always proposes the same scenes.
+//! A candidate is scored from the root index of each pixel of its thumbnail, as the product of
+//!
+//! - `boundary`: the square root of the fraction of edge pixels (see `draw::is_edge`), favouring intricate boundaries,
+//! - `balance`: the entropy of the areas of the basins, divided by its maximum, favouring basins of similar sizes,
+//! - `convergence`: the fraction of pixels that converged to a root.
+//!
+//! The thumbnails are gathered in a contact sheet, best first, and each candidate is written as a scene file at full
+//! resolution, ready to be rendered with `render`.
+
+use image::{GenericImage, Rgb32FImage};
+use num::complex::Complex;
+use super::draw::is_edge;
+use super::generators::{Generator, Rng};
+use super::scene::Scene;
+
+/// Scale of the thumbnails with respect to the full-resolution scenes
+pub const THUMBNAIL_DIVISOR: usize = 15;
+/// Margin between thumbnails in the contact sheet, in pixels
+const MARGIN: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Score {
+    pub boundary: f64,
+    pub balance: f64,
+    pub convergence: f64,
+}
+
+impl Score {
+    /// Scores the root index of each pixel of an image with `roots` roots, `roots` marking unconverged pixels
+    pub fn new(table: &[usize], width: usize, height: usize, roots: usize) -> Self {
+        let mut areas = vec![0usize; roots + 1];
+        let mut edges = 0;
+        for y in 0..height {
+            for x in 0..width {
+                areas[table[x + y * width].min(roots)] += 1;
+                if is_edge(x, y, table, width, height) {
+                    edges += 1;
+                }
+            }
+        }
+
+        let pixels = (width * height) as f64;
+        let converged = pixels - areas[roots] as f64;
+        let entropy = areas[..roots].iter()
+            .filter(|&&area| area > 0)
+            .map(|&area| area as f64 / converged)
+            .map(|p| -p * p.ln())
+            .sum::<f64>();
+
+        Self {
+            boundary: (edges as f64 / pixels).sqrt(),
+            balance: if roots > 1 { entropy / (roots as f64).ln() } else { 0.0 },
+            convergence: converged / pixels,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.boundary * self.balance * self.convergence
+    }
+}
+
+/// A random scene, with the layout its roots were generated from
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub index: usize,
+    pub layout: Generator,
+    pub scene: Scene,
+}
+
+impl Candidate {
+    /// Draws candidate `index` of the exploration with `seed`
+    pub fn random(seed: u64, index: usize) -> Self {
+        let mut rng = Rng::new(seed ^ (index as u64).wrapping_mul(0x2545f4914f6cdd1d));
+        let layout = random_layout(&mut rng);
+        let roots = layout.roots();
+
+        let center = roots.iter().sum::<Complex<f64>>() / roots.len() as f64;
+        let extent = roots.iter().map(|root| (root - center).norm()).fold(0.0, f64::max);
+        let background = match rng.next_u64() % 3 {
+            0 => None,
+            _ => Some(rng.next_u64() as usize % roots.len()),
+        };
+        let scene = Scene {
+            center: Complex::new(round(center.re), round(center.im)),
+            scale: round(1.5 * extent.max(0.5)),
+            // Closer to 2, thumbnails are mostly noise, which would get the best boundary scores
+            relaxation: round(rng.range(1.0, 1.8)),
+            background,
+            roots,
+            ..Scene::default()
+        };
+
+        Self { index, layout, scene }
+    }
+
+    /// The scene of the thumbnail, which covers the same part of the complex plane
+    pub fn thumbnail_scene(&self) -> Scene {
+        Scene {
+            width: self.scene.width / THUMBNAIL_DIVISOR,
+            height: self.scene.height / THUMBNAIL_DIVISOR,
+            ..self.scene.clone()
+        }
+    }
+
+    /// The scene file of the candidate, starting with a comment recording where it comes from
+    pub fn scene_file(&self, seed: u64) -> String {
+        format!("# explore --seed {}, candidate {}\n# roots = {}\n{}", seed, self.index, self.layout, self.scene)
+    }
+}
+
+/// Rounds generated parameters to 3 decimals, to keep the scene files readable
+fn round(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+fn random_layout(rng: &mut Rng) -> Generator {
+    let mut count = |low: usize, high: usize| low + rng.next_u64() as usize % (high - low + 1);
+    let (a, b, c) = (count(3, 8), count(2, 3), count(2, 3));
+    let seed = rng.next_u64() % 1000;
+    let layout = match rng.next_u64() % 6 {
+        0 => Generator::Random { n: a, radius: 1.0, seed },
+        1 => Generator::Unity { n: a, perturbation: round(rng.range(0.05, 0.4)), seed },
+        2 => Generator::Union(vec![
+            Generator::Polygon { n: a, radius: 1.0, phase: 0.0 },
+            Generator::Point(Complex::new(0.0, 0.0)),
+        ]),
+        3 => Generator::Rings { count: 2, n: b + 1, spacing: 1.0, twist: round(rng.range(0.0, 1.0)) },
+        4 => Generator::Spiral { n: a + 1, angle: round(rng.range(0.5, 2.5)), ratio: round(rng.range(1.05, 1.3)) },
+        _ => Generator::Lattice { columns: b, rows: c, spacing: 1.0 },
+    };
+    Generator::Rotate(round(rng.range(0.0, std::f64::consts::TAU)), Box::new(layout))
+}
+
+/// Tiles the thumbnails, best first, on a grid as square as possible, labelled with their index and score
+pub fn contact_sheet(thumbnails: &[(usize, f64, Rgb32FImage)]) -> Rgb32FImage {
+    let (width, height) = (thumbnails[0].2.width(), thumbnails[0].2.height());
+    let columns = (thumbnails.len() as f64).sqrt().ceil() as u32;
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+    let mut sheet = Rgb32FImage::new(columns * (width + MARGIN) + MARGIN, rows * (height + MARGIN) + MARGIN);
+
+    for (i, (index, score, thumbnail)) in thumbnails.iter().enumerate() {
+        let (x, y) = (MARGIN + i as u32 % columns * (width + MARGIN), MARGIN + i as u32 / columns * (height + MARGIN));
+        sheet.copy_from(thumbnail, x, y).unwrap();
+        super::overlay::draw_text(&mut sheet, (x as isize, y as isize), &format!("{} {:.3}", index, score), 2);
+    }
+    sheet
+}
+
+#[test]
+fn test_explore() {
+    // Two basins of the same size split by a vertical boundary, and a column of unconverged pixels
+    let (width, height) = (9, 10);
+    let table = (0..width * height).map(|i| match i % width {
+        0 => 2,
+        x if x < 5 => 0,
+        _ => 1,
+    }).collect::<Vec<_>>();
+    let score = Score::new(&table, width, height, 2);
+    assert!((score.balance - 1.0).abs() < 1e-12);
+    assert!((score.convergence - 8.0 / 9.0).abs() < 1e-12);
+    assert!((score.boundary - 2.0 / 3.0).abs() < 1e-12);
+    assert_eq!(Score::new(&vec![0; 90], width, height, 2).total(), 0.0);
+
+    let candidate = Candidate::random(42, 3);
+    assert_eq!(candidate, Candidate::random(42, 3));
+    assert_ne!(candidate.scene, Candidate::random(43, 3).scene);
+    let file = candidate.scene_file(42);
+    assert_eq!(Scene::parse(&file), Ok(candidate.scene.clone()));
+    let layout = file.lines().nth(1).unwrap().strip_prefix("# roots = ").unwrap();
+    assert_eq!(Generator::parse(layout).unwrap().roots(), candidate.scene.roots);
+}
@@ -0,0 +1,141 @@
+//! Raw export of the intermediate buffers, as NumPy `.npy` files.
+//!
+//! Every layer of a frame is written to its own file in `output/raw/<frame>/`:
+//!
+//! | file             | dtype  | shape          | contents                                                  |
+//! |------------------|--------|----------------|-----------------------------------------------------------|
+//! | `root.npy`       | `<u4`  | `(H, W)`       | index of the root each pixel converged to, `N` if none    |
+//! | `iterations.npy` | `<u4`  | `(H, W)`       | number of Newton-Raphson iterations                       |
+//! | `z.npy`          | `<c16` | `(H, W)`       | final value of `z`                                        |
+//! | `distance.npy`   | `<f8`  | `(H, W)`       | estimated distance to the basin boundary, in pixels       |
+//! | `proximity.npy`  | `<f8`  | `(H, W)`       | remapped distance, in `[0, 1]`, used for shading          |
+//! | `nabla.npy`      | `<f8`  | `(H, W, 2)`    | blurred gradient of the proximity, as `(dx, dy)`          |
+//! | `roots.npy`      | `<c16` | `(N,)`         | the `N` roots of the polynomial                           |
+//!
+//! The format is described in <https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html>;
+//! in short, each file is the magic string `\x93NUMPY`, the version `1.0`, a little-endian `u16` header length,
+//! an ASCII python dict describing the array (padded to a multiple of 64 bytes) and the raw, row-major data.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use num::complex::Complex;
+use distance_transform::GenericGrid;
+use super::render::Layers;
+
+/// An element type that can be stored in a `.npy` file
+pub trait NpyElement {
+    /// The numpy `dtype` descriptor of the type
+    const DESCR: &'static str;
+
+    fn write_le(&self, out: &mut impl Write) -> io::Result<()>;
+}
+
+impl NpyElement for u32 {
+    const DESCR: &'static str = "<u4";
+
+    fn write_le(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.to_le_bytes())
+    }
+}
+
+impl NpyElement for f64 {
+    const DESCR: &'static str = "<f8";
+
+    fn write_le(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.to_le_bytes())
+    }
+}
+
+impl NpyElement for Complex<f64> {
+    const DESCR: &'static str = "<c16";
+
+    fn write_le(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.re.to_le_bytes())?;
+        out.write_all(&self.im.to_le_bytes())
+    }
+}
+
+/// Writes a `.npy` array of shape `shape` to `out`; `data` must yield the elements in row-major order
+pub fn write_npy_to<T: NpyElement>(
+    out: &mut impl Write,
+    shape: &[usize],
+    data: impl IntoIterator<Item = T>
+) -> io::Result<()> {
+    let shape_str = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", T::DESCR, shape_str);
+
+    // magic (6) + version (2) + header length (2) + header + '\n' must be a multiple of 64
+    let unpadded = 10 + header.len() + 1;
+    header.extend(std::iter::repeat_n(' ', (64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+
+    let mut count = 0;
+    for x in data {
+        x.write_le(out)?;
+        count += 1;
+    }
+    debug_assert_eq!(count, shape.iter().product::<usize>());
+
+    Ok(())
+}
+
+pub fn write_npy<T: NpyElement>(
+    path: impl AsRef<Path>,
+    shape: &[usize],
+    data: impl IntoIterator<Item = T>
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_npy_to(&mut out, shape, data)?;
+    out.flush()
+}
+
+/// Writes every layer of `layers` as a `.npy` file in `dir`, see the module documentation for the list of files
+pub fn write_layers(dir: impl AsRef<Path>, layers: &Layers, roots: &[Complex<f64>]) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let shape = [layers.height, layers.width];
+    write_npy(dir.join("root.npy"), &shape, layers.table.iter().map(|&x| x as u32))?;
+    write_npy(dir.join("iterations.npy"), &shape, layers.iterations.iter().map(|&x| x as u32))?;
+    write_npy(dir.join("z.npy"), &shape, layers.values.iter().copied())?;
+    write_npy(dir.join("distance.npy"), &shape, grid_values(&layers.distance))?;
+    write_npy(dir.join("proximity.npy"), &shape, grid_values(&layers.proximity))?;
+    write_npy(
+        dir.join("nabla.npy"),
+        &[layers.height, layers.width, 2],
+        grid_values(&layers.nabla_dx).zip(grid_values(&layers.nabla_dy)).flat_map(|(dx, dy)| [dx, dy])
+    )?;
+    write_npy(dir.join("roots.npy"), &[roots.len()], roots.iter().copied())?;
+
+    Ok(())
+}
+
+/// Iterates over the values of `grid` in row-major order
+fn grid_values(grid: &GenericGrid<f64>) -> impl Iterator<Item = f64> + '_ {
+    (0..grid.height()).flat_map(move |y| (0..grid.width()).map(move |x| *grid.get(x, y).unwrap()))
+}
+
+#[test]
+fn test_npy_header() {
+    let mut buffer = Vec::new();
+    write_npy_to(&mut buffer, &[2, 3], (0..6).map(|x| x as f64)).unwrap();
+
+    assert_eq!(&buffer[0..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([buffer[8], buffer[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0);
+
+    let header = std::str::from_utf8(&buffer[10..10 + header_len]).unwrap();
+    assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }"));
+    assert!(header.ends_with('\n'));
+
+    assert_eq!(buffer.len(), 10 + header_len + 6 * 8);
+    assert_eq!(&buffer[10 + header_len + 8..10 + header_len + 16], &1.0f64.to_le_bytes());
+}
@@ -0,0 +1,331 @@
+//! Root layouts: parameterized generators of roots, and their composition.
+//!
+//! Each generator is a function returning a list of roots, and can be called from the `roots` key of a scene file,
+//! mixed with plain complex numbers; optional parameters can be left out from the end:
+//!
+//! ```text
+//! roots = polygon(5), scale(0.3, unity(7, 0.05, 42)), 0
+//! ```
+//!
+//! | call                                  | roots                                                               |
+//! |---------------------------------------|---------------------------------------------------------------------|
+//! | `polygon(n, radius = 1, phase = 0)`   | vertices of a regular polygon centered on 0, one at angle `phase`   |
+//! | `rings(count, n, spacing = 1, twist = 0)` | `count` concentric polygons of `n` vertices, of radius `spacing`, `2 spacing`, ..., each turned by `twist` from the previous one |
+//! | `spiral(n, angle, ratio)`             | `ratio^k e^(i k angle)` for `k < n`                                 |
+//! | `ringoid(n, length, growth = 8, offset = 2)` | `n` points `length` per turn, of radius `e^(growth (k - length) + offset) + 1` |
+//! | `lattice(columns, rows, spacing = 1)` | a square grid centered on 0                                         |
+//! | `random(n, radius = 1, seed = 0)`     | points uniformly distributed in the disk of radius `radius`         |
+//! | `unity(n, perturbation = 0, seed = 0)`| the `n`-th roots of unity, each moved by up to `perturbation`       |
+//! | `union(a, b, ...)`                    | all the roots of its arguments                                      |
+//! | `scale(factor, a)`                    | the roots of `a` times `factor`, which can be complex               |
+//! | `rotate(angle, a)`                    | the roots of `a` turned by `angle` radians around 0                 |
+//! | `shift(offset, a)`                    | the roots of `a` plus `offset`                                      |
+//!
+//! Angles are in radians, and the same seed always gives the same roots.
+
+use std::f64::consts::TAU;
+use std::fmt;
+use num::complex::Complex;
+use super::scene::{format_complex, parse_complex};
+
+/// Growth of the radius of `ringoid` from one point to the next, in the animation
+pub const RINGOID_GROWTH: f64 = 8.0;
+/// Offset of the exponent of the radius of `ringoid`, in the animation
+pub const RINGOID_OFFSET: f64 = 2.0;
+
+/// A small, seedable pseudo-random generator (SplitMix64), so that random layouts can be reproduced
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A float uniformly distributed in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A float uniformly distributed in `[low, high)`
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// A point uniformly distributed in the disk of radius `radius` centered on 0
+    pub fn in_disk(&mut self, radius: f64) -> Complex<f64> {
+        Complex::from_polar(radius * self.next_f64().sqrt(), TAU * self.next_f64())
+    }
+}
+
+pub fn polygon(n: usize, radius: f64, phase: f64) -> Vec<Complex<f64>> {
+    (0..n).map(|k| Complex::from_polar(radius, phase + k as f64 / n as f64 * TAU)).collect()
+}
+
+pub fn rings(count: usize, n: usize, spacing: f64, twist: f64) -> Vec<Complex<f64>> {
+    (1..=count).flat_map(|ring| polygon(n, ring as f64 * spacing, (ring - 1) as f64 * twist)).collect()
+}
+
+pub fn spiral(n: usize, angle: f64, ratio: f64) -> Vec<Complex<f64>> {
+    (0..n).map(|k| Complex::from_polar(ratio.powi(k as i32), k as f64 * angle)).collect()
+}
+
+/// A ring that turns into a spiral: with `length` points per turn, the first points lie on the unit circle and the
+/// radius grows exponentially past the `length`-th one. A `length` that is not an integer leaves a gap in the ring.
+pub fn ringoid(n: usize, length: f64, growth: f64, offset: f64) -> Vec<Complex<f64>> {
+    (0..n).map(|k| {
+        let radius = (growth * (k as f64 - length) + offset).exp() + 1.0;
+        Complex::from_polar(radius, k as f64 / length * TAU)
+    }).collect()
+}
+
+pub fn lattice(columns: usize, rows: usize, spacing: f64) -> Vec<Complex<f64>> {
+    let center = Complex::new(columns.saturating_sub(1) as f64, rows.saturating_sub(1) as f64) * spacing / 2.0;
+    (0..rows)
+        .flat_map(|y| (0..columns).map(move |x| Complex::new(x as f64, y as f64) * spacing - center))
+        .collect()
+}
+
+pub fn random(n: usize, radius: f64, seed: u64) -> Vec<Complex<f64>> {
+    let mut rng = Rng::new(seed);
+    (0..n).map(|_| rng.in_disk(radius)).collect()
+}
+
+pub fn unity(n: usize, perturbation: f64, seed: u64) -> Vec<Complex<f64>> {
+    let mut rng = Rng::new(seed);
+    polygon(n, 1.0, 0.0).into_iter().map(|z| z + rng.in_disk(perturbation)).collect()
+}
+
+/// A root layout, as written in a scene file
+#[derive(Clone, Debug, PartialEq)]
+pub enum Generator {
+    Point(Complex<f64>),
+    Polygon { n: usize, radius: f64, phase: f64 },
+    Rings { count: usize, n: usize, spacing: f64, twist: f64 },
+    Spiral { n: usize, angle: f64, ratio: f64 },
+    Ringoid { n: usize, length: f64, growth: f64, offset: f64 },
+    Lattice { columns: usize, rows: usize, spacing: f64 },
+    Random { n: usize, radius: f64, seed: u64 },
+    Unity { n: usize, perturbation: f64, seed: u64 },
+    Union(Vec<Generator>),
+    Scale(Complex<f64>, Box<Generator>),
+    Rotate(f64, Box<Generator>),
+    Shift(Complex<f64>, Box<Generator>),
+}
+
+impl Generator {
+    pub fn roots(&self) -> Vec<Complex<f64>> {
+        match self {
+            Self::Point(z) => vec![*z],
+            &Self::Polygon { n, radius, phase } => polygon(n, radius, phase),
+            &Self::Rings { count, n, spacing, twist } => rings(count, n, spacing, twist),
+            &Self::Spiral { n, angle, ratio } => spiral(n, angle, ratio),
+            &Self::Ringoid { n, length, growth, offset } => ringoid(n, length, growth, offset),
+            &Self::Lattice { columns, rows, spacing } => lattice(columns, rows, spacing),
+            &Self::Random { n, radius, seed } => random(n, radius, seed),
+            &Self::Unity { n, perturbation, seed } => unity(n, perturbation, seed),
+            Self::Union(generators) => generators.iter().flat_map(|generator| generator.roots()).collect(),
+            Self::Scale(factor, generator) => generator.roots().into_iter().map(|z| z * factor).collect(),
+            Self::Rotate(angle, generator) => {
+                let rotation = Complex::from_polar(1.0, *angle);
+                generator.roots().into_iter().map(|z| z * rotation).collect()
+            }
+            Self::Shift(offset, generator) => generator.roots().into_iter().map(|z| z + offset).collect(),
+        }
+    }
+
+    /// Parses a comma-separated list of complex numbers and generator calls, as their union
+    pub fn parse(value: &str) -> Result<Self, String> {
+        Ok(Self::Union(split_arguments(value)?.iter().map(|item| Self::parse_item(item)).collect::<Result<_, _>>()?))
+    }
+
+    fn parse_item(item: &str) -> Result<Self, String> {
+        let Some((name, arguments)) = item.split_once('(') else {
+            return Ok(Self::Point(parse_complex(item)?));
+        };
+        let arguments = arguments.strip_suffix(')').ok_or_else(|| format!("missing `)` in `{}`", item))?;
+        let args = Arguments { name: name.trim(), values: split_arguments(arguments)? };
+
+        let res = match args.name {
+            "polygon" => {
+                args.expect(1, 3)?;
+                Self::Polygon { n: args.count(0)?, radius: args.real_or(1, 1.0)?, phase: args.real_or(2, 0.0)? }
+            }
+            "rings" => {
+                args.expect(2, 4)?;
+                Self::Rings {
+                    count: args.count(0)?,
+                    n: args.count(1)?,
+                    spacing: args.real_or(2, 1.0)?,
+                    twist: args.real_or(3, 0.0)?,
+                }
+            }
+            "spiral" => {
+                args.expect(3, 3)?;
+                Self::Spiral { n: args.count(0)?, angle: args.real(1)?, ratio: args.real(2)? }
+            }
+            "ringoid" => {
+                args.expect(2, 4)?;
+                Self::Ringoid {
+                    n: args.count(0)?,
+                    length: args.real(1)?,
+                    growth: args.real_or(2, RINGOID_GROWTH)?,
+                    offset: args.real_or(3, RINGOID_OFFSET)?,
+                }
+            }
+            "lattice" => {
+                args.expect(2, 3)?;
+                Self::Lattice { columns: args.count(0)?, rows: args.count(1)?, spacing: args.real_or(2, 1.0)? }
+            }
+            "random" => {
+                args.expect(1, 3)?;
+                Self::Random { n: args.count(0)?, radius: args.real_or(1, 1.0)?, seed: args.count_or(2, 0)? as u64 }
+            }
+            "unity" => {
+                args.expect(1, 3)?;
+                Self::Unity { n: args.count(0)?, perturbation: args.real_or(1, 0.0)?, seed: args.count_or(2, 0)? as u64 }
+            }
+            "union" => Self::parse(arguments)?,
+            "scale" => {
+                args.expect(2, 2)?;
+                Self::Scale(args.complex(0)?, Box::new(Self::parse_item(&args.values[1])?))
+            }
+            "rotate" => {
+                args.expect(2, 2)?;
+                Self::Rotate(args.real(0)?, Box::new(Self::parse_item(&args.values[1])?))
+            }
+            "shift" => {
+                args.expect(2, 2)?;
+                Self::Shift(args.complex(0)?, Box::new(Self::parse_item(&args.values[1])?))
+            }
+            _ => return Err(format!("unknown generator `{}`", args.name)),
+        };
+        Ok(res)
+    }
+}
+
+/// Writes the generator as a call that `Generator::parse` reads back, with all its arguments
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Point(z) => write!(f, "{}", format_complex(*z)),
+            Self::Polygon { n, radius, phase } => write!(f, "polygon({}, {}, {})", n, radius, phase),
+            Self::Rings { count, n, spacing, twist } => write!(f, "rings({}, {}, {}, {})", count, n, spacing, twist),
+            Self::Spiral { n, angle, ratio } => write!(f, "spiral({}, {}, {})", n, angle, ratio),
+            Self::Ringoid { n, length, growth, offset } => write!(f, "ringoid({}, {}, {}, {})", n, length, growth, offset),
+            Self::Lattice { columns, rows, spacing } => write!(f, "lattice({}, {}, {})", columns, rows, spacing),
+            Self::Random { n, radius, seed } => write!(f, "random({}, {}, {})", n, radius, seed),
+            Self::Unity { n, perturbation, seed } => write!(f, "unity({}, {}, {})", n, perturbation, seed),
+            Self::Union(generators) => {
+                write!(f, "union(")?;
+                for (i, generator) in generators.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, generator)?;
+                }
+                write!(f, ")")
+            }
+            Self::Scale(factor, generator) => write!(f, "scale({}, {})", format_complex(*factor), generator),
+            Self::Rotate(angle, generator) => write!(f, "rotate({}, {})", angle, generator),
+            Self::Shift(offset, generator) => write!(f, "shift({}, {})", format_complex(*offset), generator),
+        }
+    }
+}
+
+/// Splits `value` on the commas that are not inside parentheses, trimming the parts
+fn split_arguments(value: &str) -> Result<Vec<String>, String> {
+    let mut res = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or_else(|| format!("unbalanced `)` in `{}`", value))?,
+            ',' if depth == 0 => {
+                res.push(value[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("missing `)` in `{}`", value));
+    }
+    res.push(value[start..].trim().to_string());
+    Ok(res)
+}
+
+/// The arguments of a generator call
+struct Arguments<'a> {
+    name: &'a str,
+    values: Vec<String>,
+}
+
+impl Arguments<'_> {
+    fn expect(&self, min: usize, max: usize) -> Result<(), String> {
+        if (min..=max).contains(&self.values.len()) {
+            Ok(())
+        } else if min == max {
+            Err(format!("`{}` takes {} arguments, got {}", self.name, min, self.values.len()))
+        } else {
+            Err(format!("`{}` takes {} to {} arguments, got {}", self.name, min, max, self.values.len()))
+        }
+    }
+
+    fn complex(&self, i: usize) -> Result<Complex<f64>, String> {
+        parse_complex(&self.values[i]).map_err(|e| format!("argument {} of `{}`: {}", i + 1, self.name, e))
+    }
+
+    fn real(&self, i: usize) -> Result<f64, String> {
+        self.values[i].parse().map_err(|_| format!("argument {} of `{}` should be a number, got `{}`", i + 1, self.name, self.values[i]))
+    }
+
+    fn count(&self, i: usize) -> Result<usize, String> {
+        self.values[i].parse().map_err(|_| format!("argument {} of `{}` should be a count, got `{}`", i + 1, self.name, self.values[i]))
+    }
+
+    fn real_or(&self, i: usize, default: f64) -> Result<f64, String> {
+        if i < self.values.len() { self.real(i) } else { Ok(default) }
+    }
+
+    fn count_or(&self, i: usize, default: usize) -> Result<usize, String> {
+        if i < self.values.len() { self.count(i) } else { Ok(default) }
+    }
+}
+
+#[test]
+fn test_generators() {
+    let close = |a: &[Complex<f64>], b: &[Complex<f64>]| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).norm() < 1e-12);
+    let c = |re, im| Complex::new(re, im);
+
+    assert!(close(&polygon(4, 2.0, 0.0), &[c(2.0, 0.0), c(0.0, 2.0), c(-2.0, 0.0), c(0.0, -2.0)]));
+    assert_eq!(rings(3, 5, 1.0, 0.1).len(), 15);
+    assert!(close(&lattice(2, 2, 2.0), &[c(-1.0, -1.0), c(1.0, -1.0), c(-1.0, 1.0), c(1.0, 1.0)]));
+    assert!(close(&spiral(3, TAU / 4.0, 2.0), &[c(1.0, 0.0), c(0.0, 2.0), c(-4.0, 0.0)]));
+    assert!(random(100, 3.0, 7).iter().all(|z| z.norm() <= 3.0));
+    assert_eq!(random(5, 1.0, 7), random(5, 1.0, 7));
+    assert_ne!(random(5, 1.0, 7), random(5, 1.0, 8));
+    assert!(unity(6, 0.1, 1).iter().zip(polygon(6, 1.0, 0.0)).all(|(a, b)| (a - b).norm() <= 0.1));
+
+    let generator = Generator::parse("polygon(4, 2), scale(0.5i, union(1, shift(1, lattice(1, 1)))), -1").unwrap();
+    let roots = generator.roots();
+    assert_eq!(roots.len(), 7);
+    assert!(close(&roots[4..], &[c(0.0, 0.5), c(0.0, 0.5), c(-1.0, 0.0)]));
+    assert!(close(&Generator::parse("rotate(3.141592653589793, 1+i)").unwrap().roots(), &[c(-1.0, -1.0)]));
+    assert_eq!(Generator::parse("ringoid(8, 3.5)").unwrap().roots(), ringoid(8, 3.5, RINGOID_GROWTH, RINGOID_OFFSET));
+
+    assert_eq!(Generator::parse_item(&generator.to_string()), Ok(generator));
+
+    assert!(Generator::parse("polygon()").is_err());
+    assert!(Generator::parse("polygon(2.5)").is_err());
+    assert!(Generator::parse("scale(2, polygon(3)").is_err());
+    assert!(Generator::parse("hexagon(3)").is_err());
+    assert!(Generator::parse("1, x").is_err());
+}
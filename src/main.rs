@@ -1,25 +1,52 @@
 #![feature(portable_simd)]
+#![feature(test)]
 
 extern crate scoped_threadpool;
 extern crate image;
 extern crate core_simd;
 extern crate distance_transform;
+#[cfg(test)]
+extern crate test;
 
 mod polynomial;
+mod double_double;
 mod complex_simd;
 mod newton;
 mod draw;
-
-use std::sync::{Arc, Mutex};
-use image::{RgbImage, Rgb};
+mod export;
+mod cli;
+mod output;
+mod sink;
+mod progress;
+mod scene;
+mod render;
+mod progressive;
+mod subdivide;
+mod critical;
+mod basins;
+mod dimension;
+mod overlay;
+mod generators;
+mod explore;
+mod sweep;
+#[cfg(feature = "deep")]
+mod deep;
+#[cfg(feature = "viewer")]
+mod viewer;
+
+use image::Rgb32FImage;
 use num::complex::Complex;
 use num::traits::FloatConst;
-use scoped_threadpool::Pool;
-use distance_transform::*;
 
 pub use polynomial::Polynomial;
-use newton::calc_row;
-use draw::{is_edge, gaussian_blur};
+use draw::shade;
+use cli::{Args, Command};
+use generators::Generator;
+use progress::Timings;
+use scene::Scene;
+use newton::{MultiplicityMode, NewtonForm, Precision, Root};
+use double_double::{ComplexDD, to_complex_dd};
+use render::{compute_layers, compute_samples, mismatched_pixels, Method};
 
 const WIDTH: usize = 1080 * 3;
 const HEIGHT: usize = 1350 * 3;
@@ -37,203 +64,483 @@ pub const USE_SIMD: bool = false;
 pub struct PolyInfo {
     pub f: Polynomial,
     pub df: Polynomial,
+    pub ddf: Polynomial,
     pub roots: Vec<Complex<f64>>,
+    /// The factor `A` in `z <- z - A * f(z) / f'(z)`
+    pub relaxation: f64,
+    pub epsilon: f64,
+    pub iterations: usize,
+    pub form: NewtonForm,
+    pub multiplicity_mode: MultiplicityMode,
+    pub distinct_roots: Vec<Root>,
+    /// Whether any root is treated as a multiple one
+    pub has_multiple_roots: bool,
+    pub precision: Precision,
+    /// The center of the scene in double-double precision
+    pub precise_center: ComplexDD,
+    /// The polynomial and center in arbitrary precision, for deep zooms
+    #[cfg(feature = "deep")]
+    pub deep: Option<deep::DeepInfo>,
 }
 
-fn main() {
-    let center = Complex::new(0.0, 0.0);
-    // fractal(0, Complex::new(0.0, 0.0));
-    // fractal(0, Complex::new(1.8, 0.0));
-    for frame in 0..FRAMES {
-        fractal(frame, center);
+impl PolyInfo {
+    pub fn new(scene: &Scene) -> Self {
+        let f = Polynomial::from_roots(&scene.roots);
+        let df = f.diff();
+        let ddf = df.diff();
+        let precision = Precision::for_scene(scene);
+        let precise_center = scene.precise_center.unwrap_or_else(|| to_complex_dd(scene.center));
+
+        let distinct_roots = Root::distinct(&scene.roots, &f);
+        let has_multiple_roots = distinct_roots.iter().any(|root| root.multiplicity > 1);
+
+        // println!("f(x) = {}", f);
+
+        Self {
+            #[cfg(feature = "deep")]
+            deep: match precision {
+                Precision::Arbitrary(bits) => Some(deep::DeepInfo::new(scene, &f, bits)),
+                Precision::F64 | Precision::DoubleDouble => None,
+            },
+            f,
+            df,
+            ddf,
+            roots: scene.roots.clone(),
+            relaxation: scene.relaxation,
+            epsilon: scene.epsilon,
+            iterations: scene.iterations,
+            form: scene.form,
+            multiplicity_mode: scene.multiplicity_mode,
+            distinct_roots,
+            has_multiple_roots,
+            precision,
+            precise_center,
+        }
     }
 }
 
-#[allow(dead_code)]
-fn ring(length: usize) -> Vec<Complex<f64>> {
-    let mut res = Vec::with_capacity(length);
-    for i in 0..length {
-        let a = i as f64 / length as f64 * 2.0 * f64::PI();
-        res.push(Complex::new(a.cos(), a.sin()));
-    }
-    res
-}
+fn main() {
+    let args = Args::from_env();
 
-#[allow(dead_code)]
-fn ringoid(length: usize, current_length: f64) -> Vec<Complex<f64>> {
-    let mut res = Vec::with_capacity(length);
-    for i in 0..length {
-        let a = i as f64 / current_length * 2.0 * f64::PI();
-        let r = (8.0 * (i as f64 - current_length) + 2.0).exp() + 1.0;
-        res.push(Complex::new(a.cos() * r, a.sin() * r));
+    match &args.command {
+        Command::Animate => {
+            let frames = args.frames.frames(FRAMES);
+            render_frames(&args, &frames, true, animation_scene);
+        }
+        Command::Render(path) => {
+            let scene = load_scene(path);
+            render_frames(&args, &[0], false, |_| scene.clone());
+        }
+        Command::Analyze(Some(path)) => {
+            let scene = load_scene(path);
+            analyze_frames(&[0], |_| scene.clone());
+        }
+        Command::Analyze(None) => analyze_frames(&args.frames.frames(FRAMES), animation_scene),
+        Command::Dimension(Some(path)) => {
+            let scene = load_scene(path);
+            dimension_frames(&args, &[0], |_| scene.clone());
+        }
+        Command::Dimension(None) => dimension_frames(&args, &args.frames.frames(FRAMES), animation_scene),
+        Command::Trace { start, scene: Some(path) } => trace(&args, 0, &load_scene(path), *start),
+        Command::Trace { start, scene: None } => {
+            let frame = args.frames.frames(FRAMES).first().copied().unwrap_or(0);
+            trace(&args, frame, &animation_scene(frame), *start);
+        }
+        Command::Explore(count) => explore(&args, *count),
+        Command::Sweep { columns, rows, scene: Some(path) } => sweep(&args, 0, &load_scene(path), columns, rows.as_ref()),
+        Command::Sweep { columns, rows, scene: None } => {
+            let frame = args.frames.frames(FRAMES).first().copied().unwrap_or(0);
+            sweep(&args, frame, &animation_scene(frame), columns, rows.as_ref());
+        }
+        #[cfg(feature = "viewer")]
+        Command::View(path) => {
+            let scene = match path {
+                Some(path) => load_scene(path),
+                None => animation_scene(0),
+            };
+            viewer::run(scene);
+        }
+        #[cfg(not(feature = "viewer"))]
+        Command::View(_) => {
+            eprintln!("Error: the viewer is not available, build with `--features viewer` to enable it");
+            std::process::exit(1);
+        }
     }
-    res
 }
 
-#[allow(dead_code)]
-fn spiral(length: usize, angle: f64, coeff: f64) -> Vec<Complex<f64>> {
-    let mut res = Vec::with_capacity(length);
-    for i in 0..length {
-        let r = i as f64 * angle;
-        res.push(Complex::new(r.cos() * coeff.powf(i as f64), r.sin() * coeff.powf(i as f64)));
+fn load_scene(path: &str) -> Scene {
+    match Scene::load(path) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
-    res
 }
 
-fn fractal(frame: usize, center: Complex<f64>) {
-    println!("Begin frame {}", frame);
-    let frame_ratio = frame as f64 / FRAMES as f64;
-    let frame_ratio = -(frame_ratio * f64::PI()).cos() * 0.5 + 0.5;
-    let shadow = normalize(SHADOW);
-    let mut roots = ringoid(8, frame_ratio * 7.0 + 2.0).into_iter().map(|x| Complex::new((0.02 * frame_ratio).cos(), (0.02 * frame_ratio).sin()) * x).collect::<Vec<_>>();
-    // let mut roots = ring(8).into_iter().chain(ring(8).into_iter().map(|x| 2.0 * x)).collect::<Vec<_>>();
+/// Renders `frames` into the sink selected by `args`, `scene_of` giving the scene of each frame.
+/// If `skip_existing` is set, frames that were already rendered by a previous run are skipped.
+fn render_frames(args: &Args, frames: &[usize], skip_existing: bool, scene_of: impl Fn(usize) -> Scene) {
+    let Some(&first) = frames.first() else {
+        println!("No frame to render");
+        return;
+    };
+    let first_scene = scene_of(first);
 
-    roots.push(Complex::new(0.0, 0.0));
+    let mut sink = sink::open(&args.target, args.playback, first_scene.width, first_scene.height, frames.len()).unwrap();
+    for &frame in frames {
+        if skip_existing && !args.force && sink.has_frame(frame) {
+            println!("Skipping frame {}, as it already exists", frame);
+            continue;
+        }
 
-    let bg_color_id = roots.len() - 1;
-    let f = Polynomial::from_roots(&roots);
-    let df = f.diff();
+        let scene = scene_of(frame);
+        let mut timings = Timings::new();
+        let mut basin_stats = None;
+        let image = fractal(frame, &scene, args, &mut timings, &mut basin_stats);
 
-    // println!("f(x) = {}", f);
+        println!("Encoding...");
+        timings.time("encode", || sink.write_frame(frame, &image)).unwrap();
 
-    let poly_info = PolyInfo {
-        f,
-        df,
-        roots
-    };
+        println!("Frame {} done: {}", frame, timings.summary());
+        if args.stats {
+            write_stats(frame, &scene, &timings).unwrap();
+        }
+        if let (Some(path), Some(basin_stats)) = (&args.basin_stats, &basin_stats) {
+            basins::write(&path.replace("{}", &frame.to_string()), basin_stats).unwrap();
+        }
+    }
+    sink.finish().unwrap();
+}
 
-    let mut image = RgbImage::new(WIDTH as u32, HEIGHT as u32);
-    let table = vec![0; WIDTH * HEIGHT];
+/// Prints where the critical points of the Newton map of each frame go, and which frames have attracting cycles
+fn analyze_frames(frames: &[usize], scene_of: impl Fn(usize) -> Scene) {
+    let mut bad_frames = Vec::new();
+    for &frame in frames {
+        let info = PolyInfo::new(&scene_of(frame));
+        let points = critical::analyze(&info);
+        let cycles = points.iter().filter(|point| matches!(point.fate, critical::Fate::Cycle { .. })).count();
+        let unknown = points.iter().filter(|point| point.fate == critical::Fate::Unknown).count();
+
+        println!(
+            "Frame {}: {} critical points, {} in attracting cycles, {} undecided",
+            frame,
+            points.len(),
+            cycles,
+            unknown
+        );
+        for point in points.iter() {
+            match point.fate {
+                critical::Fate::Root(_) => {}
+                critical::Fate::Cycle { period, point: z, multiplier } => println!(
+                    "    {}: cycle of period {} through {}, multiplier {:.3e}",
+                    scene::format_complex(point.z),
+                    period,
+                    scene::format_complex(z),
+                    multiplier
+                ),
+                critical::Fate::Unknown => println!("    {}: undecided", scene::format_complex(point.z)),
+            }
+        }
+        if cycles > 0 {
+            bad_frames.push(frame);
+        }
+    }
 
-    // Compute the actual fractal
-    println!("Running the Newton-Raphson algorithm...");
-    let mut pool = Pool::new(THREADS);
-    let table = Mutex::new(table);
-    pool.scoped(|scoped| {
-        let table = Arc::new(&table);
-        let poly_info = &poly_info;
-        let center = &center;
-        for y in 0..HEIGHT {
-            let table = Arc::clone(&table);
-            scoped.execute(move || {
-                let mut local_table = vec![0; WIDTH];
-                calc_row(y, &mut local_table, poly_info, center);
+    if bad_frames.is_empty() {
+        println!("No frame has an attracting cycle");
+    } else {
+        println!(
+            "Frames with attracting cycles: {}",
+            bad_frames.iter().map(|frame| frame.to_string()).collect::<Vec<_>>().join(", ")
+        );
+    }
+}
 
-                match table.lock() {
-                    Ok(mut lock) => {
-                        for x in 0..WIDTH {
-                            lock[(x + y * WIDTH) as usize] = local_table[x];
-                        }
-                    }
-                    Err(e) => panic!("{}", e),
-                }
-            });
+/// Prints the box counts and the estimated fractal dimension of the basin boundary of each frame
+fn dimension_frames(args: &Args, frames: &[usize], scene_of: impl Fn(usize) -> Scene) {
+    for &frame in frames {
+        let scene = scene_of(frame);
+        let info = PolyInfo::new(&scene);
+        println!("Frame {}: running the Newton-Raphson algorithm...", frame);
+        let (samples, _) = render::compute_samples_with(&info, &scene, args.method, true);
+        let table = samples.iter().map(|sample| sample.root).collect::<Vec<_>>();
+
+        let mut counts = dimension::box_counts(&table, scene.width, scene.height);
+        if args.refine > 0 {
+            println!("Refining the boundary...");
+            counts.extend(dimension::refined_counts(&table, &info, &scene, args.refine));
+            counts.sort_by(|a, b| b.size.total_cmp(&a.size));
+        }
+        for count in counts.iter() {
+            println!("    boxes of {} px: {}", count.size, count.count);
         }
-    });
-    let table = table.into_inner().unwrap();
 
-    // Compute "edge" matrix
-    println!("Computing edge matrix...");
-    let mut edge = BoolGrid::new(WIDTH, HEIGHT);
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            edge.set(x, y, is_edge(x, y, &table));
+        match dimension::Estimate::fit(&counts) {
+            Some(estimate) => {
+                let (low, high) = estimate.confidence_interval();
+                println!(
+                    "Frame {}: dimension {:.4} +- {:.4}, 95% confidence interval [{:.4}, {:.4}], R^2 = {:.5} over {} scales",
+                    frame,
+                    estimate.dimension,
+                    estimate.standard_error,
+                    low,
+                    high,
+                    estimate.r_squared,
+                    estimate.scales
+                );
+            }
+            None => println!("Frame {}: not enough scales with a boundary to fit a dimension", frame),
         }
     }
+}
 
-    println!("Computing proximity matrix...");
-    let mut proximity = dt2d(&edge);
+/// Prints the orbit of the Newton-Raphson method from `start`, and draws it over the frame if `args.overlay` is set
+///
+/// The orbit is computed in `f64`, like the pixels of shallow zooms.
+fn trace(args: &Args, frame: usize, scene: &Scene, start: Complex<f64>) {
+    let info = PolyInfo::new(scene);
+    let orbit = newton::trace_newton_raphson(start, &info);
+
+    println!(
+        "{:>6}  {:>22}  {:>22}  {:>10}  {:>10}  {:>4}  {:>10}",
+        "n", "re(z)", "im(z)", "|f(z)|", "|step|", "root", "distance"
+    );
+    for (n, &z) in orbit.iter().enumerate() {
+        let step = orbit.get(n + 1).map_or(String::new(), |next| format!("{:.3e}", (next - z).norm()));
+        let (root, distance) = info.roots.iter()
+            .map(|root| (z - root).norm())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, f64::NAN));
+        println!(
+            "{:>6}  {:>22.15e}  {:>22.15e}  {:>10.3e}  {:>10}  {:>4}  {:>10.3e}",
+            n,
+            z.re,
+            z.im,
+            info.f.eval(z).norm(),
+            step,
+            root,
+            distance
+        );
+    }
 
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            let p = *proximity.get(x, y).unwrap();
-            proximity.set(x, y, sigma(p.powf(0.55) / (p + 2.0).ln() / 16.0).powf(0.5));
+    let last = *orbit.last().unwrap();
+    match newton::find_color(last, &info) {
+        root if root < info.roots.len() => println!("Converged to root {} after {} iterations", root, orbit.len() - 1),
+        _ => println!("Did not converge after {} iterations", orbit.len() - 1),
+    }
+
+    if args.overlay {
+        let mut timings = Timings::new();
+        let mut image = fractal(frame, scene, args, &mut timings, &mut None);
+        let points = orbit.iter().map(|&z| scene.complex_to_pixel(z)).collect::<Vec<_>>();
+        overlay::draw_polyline(&mut image, &points, overlay::WHITE);
+        overlay::draw_ring(&mut image, points[0], 4.0, overlay::WHITE);
+
+        let mut sink = sink::open(&args.target, args.playback, scene.width, scene.height, 1).unwrap();
+        sink.write_frame(frame, &image).unwrap();
+        sink.finish().unwrap();
+        println!("Drew the orbit over frame {}", frame);
+    }
+}
+
+/// The scene of frame `frame` of the built-in animation
+fn animation_scene(frame: usize) -> Scene {
+    let frame_ratio = frame as f64 / FRAMES as f64;
+    let frame_ratio = -(frame_ratio * f64::PI()).cos() * 0.5 + 0.5;
+    let mut roots = Generator::Rotate(0.02 * frame_ratio, Box::new(Generator::Ringoid {
+        n: 8,
+        length: frame_ratio * 7.0 + 2.0,
+        growth: generators::RINGOID_GROWTH,
+        offset: generators::RINGOID_OFFSET,
+    })).roots();
+    roots.push(Complex::new(0.0, 0.0));
+
+    Scene {
+        background: Some(roots.len() - 1),
+        roots,
+        ..Scene::default()
+    }
+}
+
+/// Renders a frame; if `args.basin_stats` is set, the statistics of its basins are stored in `basin_stats`
+fn fractal(
+    frame: usize,
+    scene: &Scene,
+    args: &Args,
+    timings: &mut Timings,
+    basin_stats: &mut Option<basins::FrameStats>
+) -> Rgb32FImage {
+    println!("Begin frame {}", frame);
+    let poly_info = PolyInfo::new(scene);
+    match poly_info.precision {
+        Precision::F64 => {}
+        Precision::DoubleDouble => println!("Using double-double precision"),
+        Precision::Arbitrary(bits) if cfg!(feature = "deep") => println!("Using {} bits of precision", bits),
+        Precision::Arbitrary(_) => {
+            eprintln!("Warning: the pixels are too close to each other for double-doubles, \
+                build with `--features deep` to render this zoom");
+        }
+    }
+    if poly_info.has_multiple_roots && poly_info.multiplicity_mode != MultiplicityMode::Plain {
+        println!("Handling multiple roots with the {} Newton step", poly_info.multiplicity_mode.name());
+        for root in poly_info.distinct_roots.iter().filter(|root| root.multiplicity > 1) {
+            println!("    root {}: multiplicity {}", scene::format_complex(root.value), root.multiplicity);
         }
     }
 
-    println!("Computing shadow...");
-    let mut nabla_map_dx = GenericGrid::new(WIDTH, HEIGHT);
-    let mut nabla_map_dy = GenericGrid::new(WIDTH, HEIGHT);
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            let nabla = normalize(discrete_nabla(&proximity, x, y).unwrap());
-            nabla_map_dx.set(x, y, nabla.0);
-            nabla_map_dy.set(x, y, nabla.1);
+    let layers = compute_layers(&poly_info, scene, args.method, timings, true);
+
+    if args.verify && args.method != Method::Brute {
+        println!("Verifying against the brute-force pass...");
+        let reference = timings.time("verify", || compute_samples(&poly_info, scene, false));
+        let reference = reference.iter().map(|sample| sample.root).collect::<Vec<_>>();
+        let mismatches = mismatched_pixels(&layers.table, &reference, scene.width);
+
+        println!(
+            "{} mismatched pixels ({:.3}%)",
+            mismatches.len(),
+            mismatches.len() as f64 * 100.0 / layers.table.len() as f64
+        );
+        for (x, y) in mismatches.iter().take(10) {
+            let i = x + y * scene.width;
+            println!("    ({}, {}): root {} instead of {}", x, y, layers.table[i], reference[i]);
+        }
+        if mismatches.len() > 10 {
+            println!("    ...");
         }
     }
 
-    gaussian_blur(&mut nabla_map_dx, 4);
-    gaussian_blur(&mut nabla_map_dy, 4);
+    if args.compare_forms {
+        println!("Comparing the coefficient and root forms...");
+        timings.time("compare", || compare_forms(frame, scene)).unwrap();
+    }
 
-    println!("Drawing...");
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            let color = table[(x + y * WIDTH) as usize];
-
-            if color == poly_info.roots.len() {
-                image.put_pixel(x as u32, y as u32, Rgb([0, 0, 0]));
-            } else {
-                let a = color as f64 / poly_info.roots.len() as f64 * 2.0 * f64::PI() - 0.5;
-                let nabla = (*nabla_map_dx.get(x, y).unwrap(), *nabla_map_dy.get(x, y).unwrap());
-                let shadow_orient = if color == bg_color_id { 1.0 } else { 0.3 };
-                let s = 1.0 - (1.0 - *proximity.get(x, y).unwrap()) * (1.0 - (shadow_orient * point_mul(nabla, shadow)).max(0.0) * SHADOW_STRENGTH);
-
-                let (r, g, b) = if color == bg_color_id {
-                    let r = 50.0;
-                    let g = 50.0;
-                    let b = 55.0;
-                    (r * s, g * s, b * s)
-                } else {
-                    let s = 0.5 * s + 0.5;
-                    let r = 250.0;
-                    let g = (a.sin() + 1.0) / 2.0 * 160.0 + 70.0;
-                    let b = (-a.cos() + 1.0) / 2.0 * 160.0 + 70.0;
-                    (r * s, g * s, b * s)
-                };
-                image.put_pixel(x as u32, y as u32, Rgb([r as u8, g as u8, b as u8]));
-            }
-            // image.put_pixel(x, y, Rgb([(200.0 - re * 200.0) as u8, (200.0 - im * 200.0) as u8, 128u8]))
-        }
+    if args.raw {
+        println!("Exporting raw layers...");
+        timings.time("export", || {
+            export::write_layers(format!("output/raw/{}", frame), &layers, &poly_info.roots)
+        }).unwrap();
     }
 
-    image.save(format!("output/{}.png", frame)).unwrap();
-}
+    if args.basin_stats.is_some() {
+        println!("Computing basin statistics...");
+        *basin_stats = Some(timings.time("basins", || basins::FrameStats::new(frame, &layers, poly_info.roots.len())));
+    }
 
-fn sigma(x: f64) -> f64 {
-    1.0 - (-x).exp()
+    println!("Drawing...");
+    let mut image = timings.time("draw", || shade(&layers, scene));
+    if !args.overlays.is_empty() {
+        timings.time("overlays", || overlay::draw(&mut image, scene, &poly_info, &args.overlays));
+    }
+    image
 }
 
-fn discrete_nabla(field: &GenericGrid<f64>, x: usize, y: usize) -> Option<(f64, f64)> {
-    let dx = if x == 0 {
-        field.get(x + 1, y)? - field.get(x, y)?
-    } else if x == field.width() - 1 {
-        field.get(x, y)? - field.get(x - 1, y)?
-    } else {
-        (field.get(x + 1, y)? - field.get(x - 1, y)?) / 2.0
-    };
+/// Renders the thumbnails of `count` random scenes, and writes them to `output/explore/` as a contact sheet, best first,
+/// and a scene file per candidate
+fn explore(args: &Args, count: usize) {
+    std::fs::create_dir_all("output/explore").unwrap();
+    let mut thumbnails = Vec::with_capacity(count);
+    for index in 0..count {
+        let candidate = explore::Candidate::random(args.seed, index);
+        let scene = candidate.thumbnail_scene();
+        let info = PolyInfo::new(&scene);
+        let layers = compute_layers(&info, &scene, args.method, &mut Timings::new(), false);
+        let score = explore::Score::new(&layers.table, layers.width, layers.height, scene.roots.len());
+
+        println!(
+            "Candidate {}: score {:.3} (boundary {:.3}, balance {:.3}, convergence {:.3}), roots = {}",
+            index,
+            score.total(),
+            score.boundary,
+            score.balance,
+            score.convergence,
+            candidate.layout
+        );
+        std::fs::write(format!("output/explore/{}.txt", index), candidate.scene_file(args.seed)).unwrap();
+        thumbnails.push((index, score.total(), shade(&layers, &scene)));
+    }
 
-    let dy = if y == 0 {
-        field.get(x, y + 1)? - field.get(x, y)?
-    } else if y == field.height() - 1 {
-        field.get(x, y)? - field.get(x, y - 1)?
-    } else {
-        (field.get(x, y + 1)? - field.get(x, y - 1)?) / 2.0
-    };
+    thumbnails.sort_by(|a, b| b.1.total_cmp(&a.1));
+    println!(
+        "Best candidates: {}",
+        thumbnails.iter().take(5).map(|(index, _, _)| index.to_string()).collect::<Vec<_>>().join(", ")
+    );
+    output::save(&explore::contact_sheet(&thumbnails), "output/explore/sheet.png", output::OutputFormat::Png8).unwrap();
+    println!("Wrote the contact sheet to output/explore/sheet.png and the scenes to output/explore/<candidate>.txt");
+}
 
-    Some((dx, dy))
+/// Renders a thumbnail of `scene` for each value of `columns` and `rows`, and writes them as a single labelled image
+/// through the sink of `args`, as frame `frame`
+fn sweep(args: &Args, frame: usize, scene: &Scene, columns: &sweep::Axis, rows: Option<&sweep::Axis>) {
+    let cells = sweep::cell_scenes(scene, columns, rows);
+    let mut tiles = Vec::with_capacity(cells.len());
+    for (i, cell) in cells.iter().enumerate() {
+        println!("Rendering thumbnail {} of {}...", i + 1, cells.len());
+        let info = PolyInfo::new(cell);
+        let layers = compute_layers(&info, cell, args.method, &mut Timings::new(), false);
+        let mut tile = shade(&layers, cell);
+        if !args.overlays.is_empty() {
+            overlay::draw(&mut tile, cell, &info, &args.overlays);
+        }
+        tiles.push(tile);
+    }
+
+    // Labels are sized like the overlays of a full frame
+    let image = sweep::compose(&tiles, columns, rows, (scene.width.min(scene.height) / 400).max(1));
+    let mut sink = sink::open(&args.target, args.playback, image.width() as usize, image.height() as usize, 1).unwrap();
+    sink.write_frame(frame, &image).unwrap();
+    sink.finish().unwrap();
+    println!("Wrote the sweep of {} thumbnails", cells.len());
 }
 
-#[inline]
-fn normalize((x, y): (f64, f64)) -> (f64, f64) {
-    if x == 0.0 && y == 0.0 {
-        (0.0, 0.0)
-    } else {
-        let d = (x * x + y * y).sqrt();
-        (x / d, y / d)
+/// Renders `scene` with both forms of the Newton step, printing how far the expanded coefficients are from
+/// having the exact roots, and writing the pixels whose root differs to `output/compare/<frame>.png`
+fn compare_forms(frame: usize, scene: &Scene) -> image::ImageResult<()> {
+    let info = |form| PolyInfo::new(&Scene { form, ..scene.clone() });
+    let (coefficients, roots) = (info(NewtonForm::Coefficients), info(NewtonForm::Roots));
+
+    // At an exact root, the Newton step should be zero
+    for root in roots.distinct_roots.iter().map(|root| root.value) {
+        let [f, df] = coefficients.f.eval_with_derivs::<2>(root);
+        println!(
+            "    root {}: |f| = {:.3e}, |f / f'| = {:.3e}",
+            scene::format_complex(root),
+            f.norm(),
+            (f / df).norm()
+        );
+    }
+
+    let table = |info| compute_samples(info, scene, false).iter().map(|sample| sample.root).collect::<Vec<_>>();
+    let (coefficients, roots) = (table(&coefficients), table(&roots));
+    let mismatches = mismatched_pixels(&coefficients, &roots, scene.width);
+    println!(
+        "{} pixels converge to a different root ({:.3}%)",
+        mismatches.len(),
+        mismatches.len() as f64 * 100.0 / roots.len() as f64
+    );
+
+    let mut mask = image::GrayImage::new(scene.width as u32, scene.height as u32);
+    for (x, y) in mismatches {
+        mask.put_pixel(x as u32, y as u32, image::Luma([255]));
     }
+    std::fs::create_dir_all("output/compare")?;
+    mask.save(format!("output/compare/{}.png", frame))
 }
 
-#[inline]
-fn point_mul((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
-    x1 * x2 + y1 * y2
+/// Writes the timings of a frame to `output/stats/<frame>.json`
+fn write_stats(frame: usize, scene: &Scene, timings: &Timings) -> std::io::Result<()> {
+    std::fs::create_dir_all("output/stats")?;
+    std::fs::write(
+        format!("output/stats/{}.json", frame),
+        format!(
+            "{{\"frame\": {}, \"width\": {}, \"height\": {}, \"threads\": {}, \"timings\": {}}}\n",
+            frame,
+            scene.width,
+            scene.height,
+            THREADS,
+            timings.to_json()
+        )
+    )
 }
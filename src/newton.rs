@@ -1,24 +1,212 @@
-use super::{PolyInfo, WIDTH, HEIGHT, SCALE, EPSILON, A, ITERATIONS, USE_SIMD};
+use super::{PolyInfo, Polynomial, USE_SIMD};
 use super::complex_simd::Complex8;
+use super::scene::Scene;
+use super::double_double::{ComplexDD, to_complex_dd, to_complex_f64};
+use num::traits::Zero;
 use core_simd::f64x8;
 use num::complex::Complex;
 // use super::polynomial::Polynomial;
 
-pub fn calc_row(y: usize, table: &mut [usize], info: &PolyInfo, center: &Complex<f64>) {
+/// The outcome of running the Newton-Raphson method on a single pixel
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    /// Index of the root that `z` converged to, or `roots.len()` if it did not converge
+    pub root: usize,
+    pub iterations: usize,
+    pub z: Complex<f64>,
+    /// Estimated distance from the pixel to the boundary of its basin in the complex plane, see `distance_estimate`;
+    /// `None` for the pixels that were filled in rather than computed
+    pub distance: Option<f64>,
+}
+
+impl Default for Sample {
+    fn default() -> Self {
+        Self {
+            root: 0,
+            iterations: 0,
+            z: Complex::new(0.0, 0.0),
+            distance: None,
+        }
+    }
+}
+
+impl Sample {
+    /// The sample of an orbit that ended at `z` after `iterations` iterations
+    pub fn new(z: Complex<f64>, iterations: usize, arrival: Arrival, info: &PolyInfo) -> Self {
+        let root = find_color(z, info);
+        Self { root, iterations, z, distance: distance_estimate(arrival, root, info) }
+    }
+}
+
+/// The first value of an orbit whose Newton step is shorter than `epsilon`, or its last value if it converged before,
+/// from which the distance to the boundary is estimated, with the norm of its derivative with respect to the starting
+/// point of the orbit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arrival {
+    pub z: Complex<f64>,
+    pub derivative: f64,
+    /// Number of steps from the starting point to `z`
+    pub steps: usize,
+}
+
+/// Arithmetic used for the Newton-Raphson iteration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    F64,
+    /// Double-double floats, see `double_double.rs`
+    DoubleDouble,
+    /// MPFR floats with the given number of bits of mantissa, see `deep.rs`
+    Arbitrary(u32),
+}
+
+impl Precision {
+    /// Below this distance between neighboring pixels, relative to the magnitude of the center,
+    /// `f64` cannot tell the pixels apart well enough
+    const F64_LIMIT: f64 = 1e-12;
+    /// Same as `F64_LIMIT`, for double-doubles
+    const DOUBLE_DOUBLE_LIMIT: f64 = 1e-27;
+    /// Bits kept on top of those needed to tell the pixels apart
+    const GUARD_BITS: u32 = 32;
+
+    /// The cheapest precision that resolves the pixels of `scene`
+    pub fn for_scene(scene: &Scene) -> Self {
+        let relative = scene.pixel_size() / scene.center.norm().max(1.0);
+        if relative >= Self::F64_LIMIT {
+            Precision::F64
+        } else if relative >= Self::DOUBLE_DOUBLE_LIMIT {
+            Precision::DoubleDouble
+        } else {
+            Precision::Arbitrary(((-relative.log2()).ceil() as u32).saturating_add(Self::GUARD_BITS))
+        }
+    }
+}
+
+/// How the Newton step `f(z) / f'(z)` is computed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NewtonForm {
+    /// By evaluating the expanded coefficients of `f` and `f'`
+    Coefficients,
+    /// From the roots, as `f'(z) / f(z) = sum(m_i / (z - r_i))`, `m_i` being the multiplicity of the root `r_i`;
+    /// this does not suffer from the loss of precision of expanding many, widely spread roots into coefficients
+    Roots,
+}
+
+impl NewtonForm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "coefficients" => Some(Self::Coefficients),
+            "roots" => Some(Self::Roots),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Coefficients => "coefficients",
+            Self::Roots => "roots",
+        }
+    }
+}
+
+/// How the Newton step deals with multiple roots, around which the plain method only converges linearly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultiplicityMode {
+    /// `f / f'`, whatever the multiplicities
+    Plain,
+    /// `m * f / f'`, `m` being the multiplicity of the root `z` is close to, and `1` away from the roots
+    Modified,
+    /// The Newton step of `f / f'`, whose roots are all simple: `f f' / (f'^2 - f f'')`
+    Reformulated,
+}
+
+impl MultiplicityMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plain" => Some(Self::Plain),
+            "modified" => Some(Self::Modified),
+            "reformulated" => Some(Self::Reformulated),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Modified => "modified",
+            Self::Reformulated => "reformulated",
+        }
+    }
+}
+
+/// A distinct root of the polynomial
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Root {
+    pub value: Complex<f64>,
+    /// Number of times the root is listed in the scene
+    pub count: usize,
+    /// The multiplicity used by the modified Newton step: `count`, or more if `gcd(f, f')` shows that
+    /// the root is repeated, like when other roots are very close to it
+    pub multiplicity: usize,
+    /// Half of the distance to the closest other root, within which the modified Newton step uses `multiplicity`
+    pub radius: f64,
+}
+
+impl Root {
+    /// Groups the roots of `f` that are listed several times, and checks their multiplicity against `gcd(f, f')`
+    pub fn distinct(roots: &[Complex<f64>], f: &Polynomial) -> Vec<Root> {
+        let mut res: Vec<Root> = Vec::new();
+        for &value in roots.iter() {
+            match res.iter_mut().find(|root| root.value == value) {
+                Some(root) => root.count += 1,
+                None => res.push(Root { value, count: 1, multiplicity: 1, radius: f64::INFINITY }),
+            }
+        }
+        let values = res.iter().map(|root| root.value).collect::<Vec<_>>();
+        for root in res.iter_mut() {
+            root.radius = values.iter()
+                .filter(|&&value| value != root.value)
+                .map(|&value| (value - root.value).norm() / 2.0)
+                .fold(f64::INFINITY, f64::min);
+        }
+
+        let gcd = f.gcd(&f.diff());
+        for root in res.iter_mut() {
+            root.multiplicity = if gcd.dim() > 0 {
+                root.count.max(1 + gcd.root_multiplicity(root.value))
+            } else {
+                root.count
+            };
+        }
+        res
+    }
+}
+
+/// Size of the area covered by a pixel, relative to `|z|`, above which an iteration in extended precision
+/// can go on in `f64`, neighboring pixels being far enough apart
+pub const F64_FOOTPRINT: f64 = 1e-9;
+
+pub fn calc_row(y: usize, row: &mut [Sample], info: &PolyInfo, scene: &Scene) {
+  if info.precision != Precision::F64 {
+      for (x, sample) in row.iter_mut().enumerate() {
+          *sample = calc_pixel(x, y, info, scene);
+      }
+      return;
+  }
+
   let mut x: usize = 0;
-  if USE_SIMD {
-      while x + 7 < WIDTH {
+  if USE_SIMD && info.form == NewtonForm::Coefficients && is_plain_step(info) {
+      while x + 7 < scene.width {
           let mut c = [Complex::new(0.0, 0.0); 8];
           for i in 0..8 {
-              c[i] = Complex::new((x + i) as f64 - WIDTH as f64 / 2.0, y as f64 - HEIGHT as f64 / 2.0);
+              c[i] = scene.pixel_to_complex((x + i) as f64, y as f64);
           }
-          let mut c = Complex8::from(c) / WIDTH.max(HEIGHT) as f64 * 2.0 * SCALE + center;
+          let c = Complex8::from(c);
 
-          c = newton_raphson8(c, info);
+          let (c, iterations, arrivals) = newton_raphson8(c, info);
 
           let c: [Complex<f64>; 8] = c.into();
           for dx in 0..8 {
-              find_color(c[dx], x + dx, info, table);
+              row[x + dx] = Sample::new(c[dx], iterations, arrivals[dx], info);
           }
 
           x += 8;
@@ -29,53 +217,460 @@ pub fn calc_row(y: usize, table: &mut [usize], info: &PolyInfo, center: &Complex
       }
   }
 
-  while x < WIDTH {
-      let mut c = Complex::new(x as f64 - WIDTH as f64 / 2.0, y as f64 - HEIGHT as f64 / 2.0) / (WIDTH.max(HEIGHT)) as f64 * 2.0 * SCALE + center;
+  while x < scene.width {
+      row[x] = calc_pixel(x, y, info, scene);
+      x += 1;
+  }
+}
 
-      c = newton_raphson(c, info);
+/// Runs the Newton-Raphson method on the single pixel `(x, y)`
+pub fn calc_pixel(x: usize, y: usize, info: &PolyInfo, scene: &Scene) -> Sample {
+  #[cfg(feature = "deep")]
+  if let Some(deep) = &info.deep {
+      return deep.calc_pixel(x, y, info, scene);
+  }
+  if info.precision != Precision::F64 {
+      return calc_pixel_dd(x, y, info, scene);
+  }
 
-      find_color(c, x, info, table);
+  let c = scene.pixel_to_complex(x as f64, y as f64);
 
-      x += 1;
+  let (c, iterations, arrival) = track_newton_raphson(c, 0, 1.0, info);
+
+  Sample::new(c, iterations, arrival, info)
+}
+
+/// Same as `calc_pixel`, in double-double precision
+///
+/// The derivative of `z` with respect to the starting point is tracked alongside it, and once the area covered
+/// by the pixel has grown to `F64_FOOTPRINT`, the iteration goes on in `f64`.
+fn calc_pixel_dd(x: usize, y: usize, info: &PolyInfo, scene: &Scene) -> Sample {
+  let mut z: ComplexDD = info.precise_center + to_complex_dd(scene.pixel_offset(x as f64, y as f64));
+  let relaxation = to_complex_dd(Complex::new(info.relaxation, 0.0));
+  let mut footprint = scene.pixel_size();
+  let mut arrival = None;
+
+  for n in 0..info.iterations {
+      let step = newton_step_dd(z, info) * relaxation;
+      if arrival.is_none() && to_complex_f64(step).norm() < info.epsilon {
+          arrival = Some(Arrival { z: to_complex_f64(z), derivative: footprint / scene.pixel_size(), steps: n });
+      }
+      footprint *= newton_step_with_derivative(to_complex_f64(z), info).1.norm();
+      z = z - step;
+
+      let c = to_complex_f64(z);
+      if footprint > F64_FOOTPRINT * c.norm().max(1.0) {
+          let (c, iterations, tracked) = track_newton_raphson(c, n + 1, footprint / scene.pixel_size(), info);
+          return Sample::new(c, iterations, arrival.unwrap_or(tracked), info);
+      }
+      if n % 10 == 0 && info.roots.iter().any(|root| (c - root).norm() < info.epsilon) {
+          let last = Arrival { z: c, derivative: footprint / scene.pixel_size(), steps: n + 1 };
+          return Sample::new(c, n + 1, arrival.unwrap_or(last), info);
+      }
   }
+
+  let z = to_complex_f64(z);
+  let last = Arrival { z, derivative: footprint / scene.pixel_size(), steps: info.iterations };
+  Sample::new(z, info.iterations, arrival.unwrap_or(last), info)
 }
 
-pub fn newton_raphson(mut c: Complex<f64>, info: &PolyInfo) -> Complex<f64> {
-  for n in 0..ITERATIONS {
-      c -= info.f.eval(c) / info.df.eval(c) * A;
+/// Returns the Newton step at `c`, `f(c) / f'(c)` for simple roots, computed as selected by `info.form`
+/// and `info.multiplicity_mode`
+#[inline]
+pub fn newton_step(c: Complex<f64>, info: &PolyInfo) -> Complex<f64> {
+  match (info.form, info.multiplicity_mode) {
+      (NewtonForm::Coefficients, MultiplicityMode::Reformulated) => {
+          let [f, df, ddf] = info.f.eval_with_derivs::<3>(c);
+          f * df / (df * df - f * ddf)
+      }
+      (NewtonForm::Coefficients, mode) => {
+          let [f, df] = info.f.eval_with_derivs::<2>(c);
+          f / df * nearest_multiplicity(c, mode, info)
+      }
+      (NewtonForm::Roots, mode) => {
+          // sum(m_i / (z - r_i)) = f' / f and sum(m_i / (z - r_i)^2) = (f'^2 - f f'') / f^2
+          let mut sum = Complex::new(0.0, 0.0);
+          let mut sum_squares = Complex::new(0.0, 0.0);
+          for root in info.distinct_roots.iter() {
+              if c == root.value {
+                  return Complex::new(0.0, 0.0);
+              }
+              let inverse = 1.0 / (c - root.value);
+              sum += root.count as f64 * inverse;
+              sum_squares += root.count as f64 * inverse * inverse;
+          }
+          match mode {
+              MultiplicityMode::Reformulated => sum / sum_squares,
+              _ => nearest_multiplicity(c, mode, info) / sum,
+          }
+      }
+  }
+}
+
+/// Returns the Newton step at `c` like `newton_step`, and the derivative of the Newton map `z - A * step(z)` there
+///
+/// With `Sk = sum(1 / (z - r_i)^k)` over the roots, `f' / f = S1`, `(f'^2 - f f'') / f^2 = S2` and
+/// `(f^2 f''' - 3 f f' f'' + 2 f'^3) / (2 f^3) = S3`, so both forms get the sums, times `f^k` from the coefficients,
+/// in the same pass as the step.
+#[inline]
+pub fn newton_step_with_derivative(c: Complex<f64>, info: &PolyInfo) -> (Complex<f64>, Complex<f64>) {
+  let multiplicity = nearest_multiplicity(c, info.multiplicity_mode, info);
+  let reformulated = info.multiplicity_mode == MultiplicityMode::Reformulated;
+  let (step, (s1, s2, s3)) = match info.form {
+      NewtonForm::Coefficients if reformulated => {
+          let [f, df, ddf, dddf] = info.f.eval_with_derivs::<4>(c);
+          let s2 = df * df - f * ddf;
+          (f * df / s2, (df, s2, (f * f * dddf - 3.0 * f * df * ddf + 2.0 * df * df * df) / 2.0))
+      }
+      NewtonForm::Coefficients => {
+          let [f, df, ddf] = info.f.eval_with_derivs::<3>(c);
+          (f / df * multiplicity, (df, df * df - f * ddf, Complex::new(0.0, 0.0)))
+      }
+      NewtonForm::Roots => {
+          let (mut s1, mut s2, mut s3) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+          for root in info.distinct_roots.iter() {
+              if c == root.value {
+                  let count = Complex::new(root.count as f64, 0.0);
+                  let step_derivative = step_derivative((count, count, count), multiplicity, info);
+                  return (Complex::new(0.0, 0.0), 1.0 - info.relaxation * step_derivative);
+              }
+              let inverse = 1.0 / (c - root.value);
+              s1 += root.count as f64 * inverse;
+              s2 += root.count as f64 * inverse * inverse;
+              s3 += root.count as f64 * inverse * inverse * inverse;
+          }
+          (if reformulated { s1 / s2 } else { multiplicity / s1 }, (s1, s2, s3))
+      }
+  };
+  (step, 1.0 - info.relaxation * step_derivative((s1, s2, s3), multiplicity, info))
+}
+
+/// The derivative of the Newton step, given the sums `Sk` of `newton_step_with_derivative`, or these sums times `λ^k`
+/// for any `λ`: the step is `m / S1` with the multiplicity `m`, of derivative `m S2 / S1^2`, and the reformulated step
+/// is `S1 / S2`, of derivative `2 S1 S3 / S2^2 - 1`
+#[inline]
+fn step_derivative(
+  (s1, s2, s3): (Complex<f64>, Complex<f64>, Complex<f64>),
+  multiplicity: f64,
+  info: &PolyInfo
+) -> Complex<f64> {
+  match info.multiplicity_mode {
+      MultiplicityMode::Reformulated => 2.0 * s1 * s3 / (s2 * s2) - 1.0,
+      _ => multiplicity * s2 / (s1 * s1),
+  }
+}
+
+/// Estimates the distance from the starting point of an orbit to the boundary of its basin, given its arrival near
+/// the root with index `root`
+///
+/// This is the Koebe estimate `(e^G - 1) / |G'|` of a potential `G` of the basin, which is constant along the orbit:
+/// with the error `e = |z - r|` after `n` steps, `G = 2^-n ln(1 / e)` near a superattracting root, where the error is
+/// squared at each step, and `G = ln(1 / (e λ^-n))` near a root whose multiplier `λ` scales it. Close to the boundary,
+/// where `G` is small, this is the usual `G / |G'|`; close to `r` it stays finite instead of vanishing.
+///
+/// Orbits that did not converge are on the boundary, or in an attracting cycle.
+pub fn distance_estimate(arrival: Arrival, root: usize, info: &PolyInfo) -> Option<f64> {
+  if root >= info.roots.len() {
+      return Some(0.0);
+  }
+  // Orbits can start right on their root, where the estimate has a finite limit
+  let error = (arrival.z - info.roots[root]).norm().max(f64::MIN_POSITIVE);
+  let multiplier = root_multiplier(info.roots[root], info);
+  let distance = if multiplier <= info.epsilon {
+      let scale = 2f64.powi(arrival.steps.min(1000) as i32);
+      scale * error * ((-error.ln()).max(1.0) / scale).exp_m1() / arrival.derivative
+  } else {
+      let potential = -error.ln() + arrival.steps as f64 * multiplier.ln();
+      error * potential.max(1.0).exp_m1() / arrival.derivative
+  };
+  distance.is_finite().then_some(distance)
+}
+
+/// The multiplier `|N'(r)|` of the Newton map at the root `r`: near a root listed `k` times, the sums `Sk` of
+/// `newton_step_with_derivative` are about `k / (z - r)^k`
+fn root_multiplier(r: Complex<f64>, info: &PolyInfo) -> f64 {
+  let count = info.distinct_roots.iter()
+      .min_by(|a, b| (r - a.value).norm_sqr().total_cmp(&(r - b.value).norm_sqr()))
+      .map_or(1, |root| root.count);
+  let count = Complex::new(count as f64, 0.0);
+  let multiplicity = nearest_multiplicity(r, info.multiplicity_mode, info);
+  (1.0 - info.relaxation * step_derivative((count, count, count), multiplicity, info)).norm()
+}
+
+/// Whether the Newton step is just `f / f'`
+#[inline]
+fn is_plain_step(info: &PolyInfo) -> bool {
+  match info.multiplicity_mode {
+      MultiplicityMode::Plain => true,
+      MultiplicityMode::Modified => !info.has_multiple_roots,
+      MultiplicityMode::Reformulated => false,
+  }
+}
+
+/// The factor of the modified Newton step: the multiplicity of the root `c` is within the radius of, if any.
+/// Far from the roots, `f / f'` is about `z / n` for `n` roots, so a larger factor would change the dynamics there,
+/// making infinity repel once `A m / n > 2`.
+#[inline]
+pub fn nearest_multiplicity(c: Complex<f64>, mode: MultiplicityMode, info: &PolyInfo) -> f64 {
+  if mode != MultiplicityMode::Modified || is_plain_step(info) {
+      return 1.0;
+  }
+  info.distinct_roots.iter()
+      .find(|root| (c - root.value).norm() < root.radius)
+      .map_or(1.0, |root| root.multiplicity as f64)
+}
+
+/// Same as `newton_step`, in double-double precision
+fn newton_step_dd(z: ComplexDD, info: &PolyInfo) -> ComplexDD {
+  let multiplicity = to_complex_dd(Complex::new(nearest_multiplicity(to_complex_f64(z), info.multiplicity_mode, info), 0.0));
+
+  match (info.form, info.multiplicity_mode) {
+      (NewtonForm::Coefficients, MultiplicityMode::Reformulated) => {
+          let (f, df, ddf) = (info.f.eval_dd(z), info.df.eval_dd(z), info.ddf.eval_dd(z));
+          f * df / (df * df - f * ddf)
+      }
+      (NewtonForm::Coefficients, _) => info.f.eval_dd(z) / info.df.eval_dd(z) * multiplicity,
+      (NewtonForm::Roots, mode) => {
+          let mut sum = to_complex_dd(Complex::new(0.0, 0.0));
+          let mut sum_squares = sum;
+          for root in info.distinct_roots.iter() {
+              let difference = z - to_complex_dd(root.value);
+              if difference.is_zero() {
+                  return difference;
+              }
+              let count = to_complex_dd(Complex::new(root.count as f64, 0.0));
+              let inverse = to_complex_dd(Complex::new(1.0, 0.0)) / difference;
+              sum = sum + count * inverse;
+              sum_squares = sum_squares + count * inverse * inverse;
+          }
+          match mode {
+              MultiplicityMode::Reformulated => sum / sum_squares,
+              _ => multiplicity / sum,
+          }
+      }
+  }
+}
+
+/// Returns the last value of `c` and the number of iterations that were done
+pub fn newton_raphson(c: Complex<f64>, info: &PolyInfo) -> (Complex<f64>, usize) {
+  resume_newton_raphson(c, 0, info)
+}
+
+/// Continues the Newton-Raphson method from `c`, the value after `start` iterations
+pub fn resume_newton_raphson(c: Complex<f64>, start: usize, info: &PolyInfo) -> (Complex<f64>, usize) {
+  visit_newton_raphson(c, start, info, |_| {})
+}
+
+/// Runs the Newton-Raphson method from `c` like `newton_raphson`, returning every value of the orbit, `c` included
+pub fn trace_newton_raphson(c: Complex<f64>, info: &PolyInfo) -> Vec<Complex<f64>> {
+  let mut orbit = vec![c];
+  visit_newton_raphson(c, 0, info, |z| orbit.push(z));
+  orbit
+}
+
+/// Same as `resume_newton_raphson`, also returning the arrival of the orbit near its root
+///
+/// `derivative` is the norm of the derivative of `c` with respect to the starting point of the orbit, 1 if it is `c`,
+/// and is multiplied by `|N'(z)|` along the orbit until the arrival, `N` being the Newton map.
+pub fn track_newton_raphson(
+  mut c: Complex<f64>,
+  start: usize,
+  mut derivative: f64,
+  info: &PolyInfo
+) -> (Complex<f64>, usize, Arrival) {
+  let mut arrival = None;
+  for n in start..info.iterations {
+      let step = match arrival {
+          Some(_) => newton_step(c, info) * info.relaxation,
+          None => {
+              let (step, map_derivative) = newton_step_with_derivative(c, info);
+              let step = step * info.relaxation;
+              if step.norm() < info.epsilon {
+                  arrival = Some(Arrival { z: c, derivative, steps: n });
+              }
+              derivative *= map_derivative.norm();
+              step
+          }
+      };
+      c -= step;
       if n % 10 == 0 {
           for root in info.roots.iter() {
-              if (c - root).norm() < EPSILON {
-                  return c;
+              if (c - root).norm() < info.epsilon {
+                  return (c, n + 1, arrival.unwrap_or(Arrival { z: c, derivative, steps: n + 1 }));
               }
           }
       }
   }
 
-  c
+  (c, info.iterations, arrival.unwrap_or(Arrival { z: c, derivative, steps: info.iterations }))
 }
 
-pub fn newton_raphson8(mut c: Complex8, info: &PolyInfo) -> Complex8 {
-  for n in 0..ITERATIONS {
-      c -= info.f.eval8(c) / info.df.eval8(c) * A;
+/// Same as `resume_newton_raphson`, calling `visit` with the value after each iteration
+#[inline]
+fn visit_newton_raphson(
+  mut c: Complex<f64>,
+  start: usize,
+  info: &PolyInfo,
+  mut visit: impl FnMut(Complex<f64>)
+) -> (Complex<f64>, usize) {
+  for n in start..info.iterations {
+      c -= newton_step(c, info) * info.relaxation;
+      visit(c);
       if n % 10 == 0 {
           for root in info.roots.iter() {
-              if (c - root).norm().lanes_lt(f64x8::splat(EPSILON)).all() {
-                  return c;
+              if (c - root).norm() < info.epsilon {
+                  return (c, n + 1);
               }
           }
       }
   }
 
-  c
+  (c, info.iterations)
 }
 
-fn find_color(c: Complex<f64>, x: usize, info: &PolyInfo, table: &mut [usize]) {
+/// Runs the plain Newton-Raphson method on 8 points at once like `track_newton_raphson`, returning the arrival of
+/// each of them
+pub fn newton_raphson8(mut c: Complex8, info: &PolyInfo) -> (Complex8, usize, [Arrival; 8]) {
+  // `Complex8::norm` is the squared norm
+  let mut derivative = f64x8::splat(1.0);
+  let mut arrival = (c, derivative, f64x8::splat(0.0));
+  let mut arrived = f64x8::splat(0.0).lanes_gt(f64x8::splat(0.0));
+  type Lanes = (Complex8, f64x8, f64x8);
+  let arrivals = |(z, derivative, steps): Lanes, arrived: core_simd::mask64x8, last: (Complex8, f64x8, usize)| {
+      let (z, last_z): ([Complex<f64>; 8], [Complex<f64>; 8]) = (z.into(), last.0.into());
+      let (derivative, last_derivative) = (derivative.to_array(), last.1.to_array());
+      let (steps, arrived) = (steps.to_array(), arrived.to_array());
+      std::array::from_fn(|i| match arrived[i] {
+          true => Arrival { z: z[i], derivative: derivative[i].sqrt(), steps: steps[i] as usize },
+          false => Arrival { z: last_z[i], derivative: last_derivative[i].sqrt(), steps: last.2 },
+      })
+  };
+
+  for n in 0..info.iterations {
+      let [f, df, ddf] = info.f.eval_with_derivs8::<3>(c);
+      let step = f / df * info.relaxation;
+      let now = step.norm().lanes_lt(f64x8::splat(info.epsilon * info.epsilon)) & !arrived;
+      arrival = (
+          Complex8 { re: now.select(c.re, arrival.0.re), im: now.select(c.im, arrival.0.im) },
+          now.select(derivative, arrival.1),
+          now.select(f64x8::splat(n as f64), arrival.2)
+      );
+      arrived |= now;
+      derivative *= ((f * ddf / (df * df)) * info.relaxation + Complex::new(1.0 - info.relaxation, 0.0)).norm();
+      c -= step;
+      if n % 10 == 0 {
+          for root in info.roots.iter() {
+              if (c - root).norm().lanes_lt(f64x8::splat(info.epsilon)).all() {
+                  return (c, n + 1, arrivals(arrival, arrived, (c, derivative, n + 1)));
+              }
+          }
+      }
+  }
+
+  (c, info.iterations, arrivals(arrival, arrived, (c, derivative, info.iterations)))
+}
+
+pub fn find_color(c: Complex<f64>, info: &PolyInfo) -> usize {
     let mut color = info.roots.len();
     for i in 0..info.roots.len() {
-        if (c - info.roots[i]).norm() < EPSILON {
+        if (c - info.roots[i]).norm() < info.epsilon {
             color = i;
         }
     }
-    table[x] = color;
+    color
+}
+
+#[test]
+fn test_precision() {
+    let scene = |scale| Scene { scale, center: Complex::new(0.5, 0.0), ..Scene::default() };
+    assert_eq!(Precision::for_scene(&scene(1.0)), Precision::F64);
+    assert_eq!(Precision::for_scene(&scene(1e-20)), Precision::DoubleDouble);
+    assert!(matches!(Precision::for_scene(&scene(1e-40)), Precision::Arbitrary(bits) if bits > 128));
+
+    // At shallow zooms, double-doubles give the same basins
+    let scene = Scene {
+        width: 40,
+        height: 30,
+        scale: 2.0,
+        relaxation: 1.0,
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0), Complex::new(0.0, 1.0)],
+        ..Scene::default()
+    };
+    let mut info = PolyInfo::new(&scene);
+    let f64_pixels = (0..30).flat_map(|y| (0..40).map(move |x| (x, y)))
+        .map(|(x, y)| calc_pixel(x, y, &info, &scene).root)
+        .collect::<Vec<_>>();
+    info.precision = Precision::DoubleDouble;
+    let dd_pixels = (0..30).flat_map(|y| (0..40).map(move |x| (x, y)))
+        .map(|(x, y)| calc_pixel(x, y, &info, &scene).root)
+        .collect::<Vec<_>>();
+    assert_eq!(f64_pixels, dd_pixels);
+}
+
+#[test]
+fn test_newton_forms() {
+    let roots = vec![Complex::new(1.0, 0.0), Complex::new(-0.5, 2.0), Complex::new(-0.5, 2.0), Complex::new(0.0, -1.0)];
+    let info = |form, multiplicity_mode| PolyInfo::new(&Scene { roots: roots.clone(), form, multiplicity_mode, ..Scene::default() });
+
+    for mode in [MultiplicityMode::Plain, MultiplicityMode::Modified, MultiplicityMode::Reformulated] {
+        let (coefficients, roots) = (info(NewtonForm::Coefficients, mode), info(NewtonForm::Roots, mode));
+        for z in [Complex::new(0.3, 0.4), Complex::new(-2.0, 1.0), Complex::new(5.0, -3.0)] {
+            let (a, b) = (newton_step(z, &coefficients), newton_step(z, &roots));
+            assert!((a - b).norm() < 1e-12 * a.norm(), "{} != {}", a, b);
+            let (step, a) = newton_step_with_derivative(z, &coefficients);
+            let b = newton_step_with_derivative(z, &roots).1;
+            assert_eq!(step, newton_step(z, &coefficients));
+            assert!((a - b).norm() < 1e-9 * a.norm(), "{} != {}", a, b);
+        }
+        assert_eq!(newton_step(Complex::new(0.0, -1.0), &roots), Complex::new(0.0, 0.0));
+    }
+}
+
+#[test]
+fn test_multiple_roots() {
+    let roots = vec![Complex::new(1.0, 0.0), Complex::new(2.0, 0.0), Complex::new(2.0, 0.0), Complex::new(2.0, 0.0), Complex::new(-1.0, 1.0)];
+    let distinct = Root::distinct(&roots, &Polynomial::from_roots(&roots));
+    assert_eq!(distinct.len(), 3);
+    assert_eq!(distinct[1], Root { value: Complex::new(2.0, 0.0), count: 3, multiplicity: 3, radius: 0.5 });
+    assert_eq!(distinct[2].multiplicity, 1);
+
+    // Near the triple root, plain Newton only gains a third of a digit per step, and both other methods converge
+    // quadratically; from the coefficients, where the triple root is only known to about 1e-5, plain Newton stalls
+    let iterations = |form, multiplicity_mode| {
+        let scene = Scene { roots: roots.clone(), relaxation: 1.0, epsilon: 1e-6, form, multiplicity_mode, ..Scene::default() };
+        newton_raphson(Complex::new(2.3, 0.2), &PolyInfo::new(&scene))
+    };
+    let (_, plain) = iterations(NewtonForm::Roots, MultiplicityMode::Plain);
+    assert!(plain > 30);
+    for multiplicity_mode in [MultiplicityMode::Modified, MultiplicityMode::Reformulated] {
+        let (z, count) = iterations(NewtonForm::Roots, multiplicity_mode);
+        assert!((z - roots[1]).norm() < 1e-6 && count < plain / 2, "{} after {} iterations", z, count);
+    }
+    assert_eq!(iterations(NewtonForm::Coefficients, MultiplicityMode::Plain).1, 1000);
+    assert!(iterations(NewtonForm::Coefficients, MultiplicityMode::Modified).1 < 1000);
+    assert!(iterations(NewtonForm::Coefficients, MultiplicityMode::Reformulated).1 < 1000);
+
+    // Away from the triple root, and in particular far from all the roots, the modified step is the plain one
+    let info = |multiplicity_mode| PolyInfo::new(&Scene { roots: roots.clone(), multiplicity_mode, ..Scene::default() });
+    let (modified, plain) = (info(MultiplicityMode::Modified), info(MultiplicityMode::Plain));
+    for c in [Complex::new(100.0, 50.0), Complex::new(1.2, 0.0), Complex::new(2.6, 0.0)] {
+        assert_eq!(newton_step(c, &modified), newton_step(c, &plain));
+    }
+    assert_ne!(newton_step(Complex::new(2.4, 0.0), &modified), newton_step(Complex::new(2.4, 0.0), &plain));
+}
+
+#[test]
+fn test_distance_estimate() {
+    // The basins of 1 and -1 are split by the imaginary axis, whether the roots are superattracting or not
+    for relaxation in [1.0, 1.5] {
+        let roots = vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)];
+        let info = PolyInfo::new(&Scene { roots, relaxation, ..Scene::default() });
+        for (re, im) in [(0.01, 0.3), (0.2, -1.0), (-0.5, 0.5), (1.0, 0.0), (3.0, 2.0)] {
+            let c = Complex::new(re, im);
+            let (z, iterations, arrival) = track_newton_raphson(c, 0, 1.0, &info);
+            let distance = Sample::new(z, iterations, arrival, &info).distance.unwrap();
+            let ratio = distance / c.re.abs();
+            assert!((0.5..2.0).contains(&ratio), "{} at {} with relaxation {}", distance, c, relaxation);
+        }
+    }
 }
@@ -0,0 +1,140 @@
+//! Encoding of the shaded image into the different output formats.
+//!
+//! Shading is done in floating point (see `draw::shade`), with values in `[0, 1]` that are meant to be
+//! displayed as sRGB; they are only quantized or converted to linear light here, right before encoding.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use image::{ImageBuffer, ImageFormat, ImageResult, Rgb, Rgb32FImage, RgbImage};
+use image::codecs::hdr::HdrEncoder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 8 bits per channel PNG
+    Png8,
+    /// 16 bits per channel PNG
+    Png16,
+    /// 16 bits per channel TIFF
+    Tiff16,
+    /// 32-bit float OpenEXR, in linear light
+    Exr,
+    /// Radiance HDR, in linear light
+    Hdr,
+    /// 8 bits per channel lossless WebP
+    WebP,
+}
+
+impl OutputFormat {
+    pub const NAMES: &'static str = "png, png16, tiff, exr, hdr, webp";
+
+    /// Parses the name of a format, as given to `--format`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" | "png8" => Some(Self::Png8),
+            "png16" => Some(Self::Png16),
+            "tiff" | "tif" | "tiff16" => Some(Self::Tiff16),
+            "exr" => Some(Self::Exr),
+            "hdr" => Some(Self::Hdr),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension of `path`; `.png` files are 8 bits per channel
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?;
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png8),
+            "tiff" | "tif" => Some(Self::Tiff16),
+            "exr" => Some(Self::Exr),
+            "hdr" => Some(Self::Hdr),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png8 | Self::Png16 => "png",
+            Self::Tiff16 => "tiff",
+            Self::Exr => "exr",
+            Self::Hdr => "hdr",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+/// Encodes `image` as `format` and writes it to `path`
+pub fn save(image: &Rgb32FImage, path: impl AsRef<Path>, format: OutputFormat) -> ImageResult<()> {
+    let path = path.as_ref();
+    match format {
+        OutputFormat::Png8 => quantize8(image).save_with_format(path, ImageFormat::Png),
+        OutputFormat::Png16 => quantize16(image).save_with_format(path, ImageFormat::Png),
+        OutputFormat::Tiff16 => quantize16(image).save_with_format(path, ImageFormat::Tiff),
+        OutputFormat::WebP => quantize8(image).save_with_format(path, ImageFormat::WebP),
+        OutputFormat::Exr => to_linear(image).save_with_format(path, ImageFormat::OpenExr),
+        OutputFormat::Hdr => {
+            let linear = to_linear(image);
+            let pixels = linear.pixels().copied().collect::<Vec<_>>();
+            HdrEncoder::new(BufWriter::new(File::create(path)?))
+                .encode(&pixels, linear.width() as usize, linear.height() as usize)
+        }
+    }
+}
+
+pub fn quantize8(image: &Rgb32FImage) -> RgbImage {
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let Rgb([r, g, b]) = *image.get_pixel(x, y);
+        Rgb([quantize_channel8(r), quantize_channel8(g), quantize_channel8(b)])
+    })
+}
+
+pub fn quantize16(image: &Rgb32FImage) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let Rgb([r, g, b]) = *image.get_pixel(x, y);
+        Rgb([quantize_channel16(r), quantize_channel16(g), quantize_channel16(b)])
+    })
+}
+
+#[inline]
+fn quantize_channel8(x: f32) -> u8 {
+    (x.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8
+}
+
+#[inline]
+fn quantize_channel16(x: f32) -> u16 {
+    (x.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+/// Converts an sRGB-encoded image to linear light, as expected by the floating point formats
+pub fn to_linear(image: &Rgb32FImage) -> Rgb32FImage {
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let Rgb([r, g, b]) = *image.get_pixel(x, y);
+        Rgb([srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)])
+    })
+}
+
+#[inline]
+fn srgb_to_linear(x: f32) -> f32 {
+    let x = x.max(0.0);
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[test]
+fn test_output_format() {
+    assert_eq!(OutputFormat::from_path("output/12.png"), Some(OutputFormat::Png8));
+    assert_eq!(OutputFormat::from_path("output/12.EXR"), Some(OutputFormat::Exr));
+    assert_eq!(OutputFormat::from_path("output/12"), None);
+    assert_eq!(OutputFormat::from_name("png16"), Some(OutputFormat::Png16));
+    assert_eq!(OutputFormat::from_name("tif"), Some(OutputFormat::Tiff16));
+
+    assert_eq!(quantize_channel8(1.5), 255);
+    assert_eq!(quantize_channel16(-0.1), 0);
+    assert_eq!(quantize_channel16(0.5), 32768);
+    assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,376 @@
+//! Annotations drawn over rendered images, in pixel coordinates (see `Scene::complex_to_pixel`).
+//!
+//! Besides the drawing primitives, this has the overlays that can be selected with `--overlays`: markers at the roots
+//! and at the critical points of the Newton map, the axes with ticks labelled in complex coordinates, the unit circle,
+//! and the bounds of the viewport. Labels use a built-in 3x5 pixel font, scaled with the size of the image.
+
+use image::{Rgb, Rgb32FImage};
+use num::complex::Complex;
+use super::PolyInfo;
+use super::critical::critical_points;
+use super::draw::basin_color;
+use super::scene::Scene;
+
+pub const WHITE: Rgb<f32> = Rgb([1.0, 1.0, 1.0]);
+pub const BLACK: Rgb<f32> = Rgb([0.0, 0.0, 0.0]);
+
+/// An annotation that can be drawn over a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overlay {
+    /// A marker at each root, filled with the color of its basin
+    Roots,
+    /// A cross at each critical point of the relaxed Newton map, see `critical.rs`
+    Critical,
+    /// The real and imaginary axes, with labelled ticks; when an axis is out of view, its ticks are drawn on the border
+    Axes,
+    UnitCircle,
+    /// A border around the image, labelled with the coordinates of its corners
+    Viewport,
+}
+
+impl Overlay {
+    pub const NAMES: &'static str = "roots, critical, axes, circle, viewport, all";
+    pub const ALL: [Overlay; 5] = [Self::Roots, Self::Critical, Self::Axes, Self::UnitCircle, Self::Viewport];
+
+    /// Parses a comma-separated list of overlays, `all` standing for all of them
+    pub fn parse_list(list: &str) -> Option<Vec<Self>> {
+        let mut res = Vec::new();
+        for name in list.split(',').map(|name| name.trim()) {
+            match name {
+                "roots" => res.push(Self::Roots),
+                "critical" => res.push(Self::Critical),
+                "axes" => res.push(Self::Axes),
+                "circle" => res.push(Self::UnitCircle),
+                "viewport" => res.push(Self::Viewport),
+                "all" => res.extend(Self::ALL),
+                _ => return None,
+            }
+        }
+        Some(res)
+    }
+}
+
+/// Draws `overlays` over the frame of `scene`, in the order of `Overlay::ALL` so that markers stay on top
+pub fn draw(image: &mut Rgb32FImage, scene: &Scene, info: &PolyInfo, overlays: &[Overlay]) {
+    let scale = text_scale(image);
+    for overlay in Overlay::ALL.iter().rev().filter(|overlay| overlays.contains(overlay)) {
+        match overlay {
+            Overlay::Viewport => draw_viewport(image, scene, scale),
+            Overlay::UnitCircle => {
+                let points = (0..=720)
+                    .map(|i| scene.complex_to_pixel(Complex::from_polar(1.0, i as f64 / 720.0 * std::f64::consts::TAU)))
+                    .collect::<Vec<_>>();
+                draw_polyline(image, &points, WHITE);
+            }
+            Overlay::Axes => draw_axes(image, scene, scale),
+            Overlay::Critical => {
+                for z in critical_points(info) {
+                    draw_cross(image, scene.complex_to_pixel(z), 3.0 * scale as f64, WHITE);
+                }
+            }
+            Overlay::Roots => {
+                for (root, &z) in scene.roots.iter().enumerate() {
+                    let (r, g, b) = basin_color(root, scene);
+                    let color = Rgb([(r / 255.0) as f32, (g / 255.0) as f32, (b / 255.0) as f32]);
+                    let center = scene.complex_to_pixel(z);
+                    let radius = 3.0 * scale as f64;
+                    draw_disk(image, center, radius, color);
+                    draw_ring(image, center, radius + 1.0, WHITE);
+                }
+            }
+        }
+    }
+}
+
+/// Size of the pixels of the font, so that labels stay readable on large images
+fn text_scale(image: &Rgb32FImage) -> usize {
+    (image.width().min(image.height()) as usize / 400).max(1)
+}
+
+/// The points of the complex plane at the top-left and bottom-right corners of the image; since pixel `(x, y)` is
+/// centered on `pixel_to_complex(x, y)`, those are half a pixel away from the centers of the corner pixels
+fn bounds(scene: &Scene) -> (Complex<f64>, Complex<f64>) {
+    (scene.pixel_to_complex(-0.5, -0.5), scene.pixel_to_complex(scene.width as f64 - 0.5, scene.height as f64 - 0.5))
+}
+
+fn draw_viewport(image: &mut Rgb32FImage, scene: &Scene, scale: usize) {
+    let (width, height) = (scene.width as f64, scene.height as f64);
+    let corners = [(1.0, 1.0), (width - 2.0, 1.0), (width - 2.0, height - 2.0), (1.0, height - 2.0), (1.0, 1.0)];
+    draw_polyline(image, &corners, WHITE);
+
+    let margin = 3 * scale as isize;
+    let (top_left, bottom_right) = bounds(scene);
+    let format = |z: Complex<f64>| format!(
+        "{}{}{}i",
+        format_tick(z.re, scene.pixel_size()),
+        if z.im < 0.0 { "-" } else { "+" },
+        format_tick(z.im.abs(), scene.pixel_size())
+    );
+    let (top_left, bottom_right) = (format(top_left), format(bottom_right));
+    draw_text(image, (margin, margin), &top_left, scale);
+    let (text_width, text_height) = text_size(&bottom_right, scale);
+    draw_text(image, (scene.width as isize - margin - text_width, scene.height as isize - margin - text_height), &bottom_right, scale);
+}
+
+fn draw_axes(image: &mut Rgb32FImage, scene: &Scene, scale: usize) {
+    let (width, height) = (scene.width as f64, scene.height as f64);
+    let origin = scene.complex_to_pixel(Complex::new(0.0, 0.0));
+    let (top_left, bottom_right) = bounds(scene);
+    let tick = 2.0 * scale as f64 + 1.0;
+    let margin = tick + scale as f64 * 2.0;
+
+    // The real axis is horizontal; out of view, its ticks go along the bottom of the image
+    let y = origin.1.clamp(margin, height - margin - text_size("0", scale).1 as f64);
+    if (0.0..height).contains(&origin.1) {
+        draw_polyline(image, &[(0.0, origin.1), (width, origin.1)], WHITE);
+    }
+    let step = tick_step(bottom_right.re - top_left.re);
+    for k in (top_left.re / step).ceil() as i64..=(bottom_right.re / step).floor() as i64 {
+        let x = scene.complex_to_pixel(Complex::new(k as f64 * step, 0.0)).0;
+        draw_polyline(image, &[(x, y - tick), (x, y + tick)], WHITE);
+        let label = format_tick(k as f64 * step, step);
+        let text_width = text_size(&label, scale).0;
+        draw_text(image, (x.round() as isize - text_width / 2, (y + margin).round() as isize), &label, scale);
+    }
+
+    // The imaginary axis is vertical, and its ticks go along the left of the image when out of view
+    let x = origin.0.clamp(margin, width - margin);
+    if (0.0..width).contains(&origin.0) {
+        draw_polyline(image, &[(origin.0, 0.0), (origin.0, height)], WHITE);
+    }
+    let step = tick_step(bottom_right.im - top_left.im);
+    for k in (top_left.im / step).ceil() as i64..=(bottom_right.im / step).floor() as i64 {
+        if k == 0 && (0.0..width).contains(&origin.0) && (0.0..height).contains(&origin.1) {
+            continue;
+        }
+        let y = scene.complex_to_pixel(Complex::new(0.0, k as f64 * step)).1;
+        draw_polyline(image, &[(x - tick, y), (x + tick, y)], WHITE);
+        let label = format_tick(k as f64 * step, step) + "i";
+        let text_height = text_size(&label, scale).1;
+        draw_text(image, ((x + margin).round() as isize, y.round() as isize - text_height / 2), &label, scale);
+    }
+}
+
+/// A round step between ticks, 1, 2 or 5 times a power of ten, giving about 8 ticks over `extent`
+fn tick_step(extent: f64) -> f64 {
+    let rough = extent / 8.0;
+    let power = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].into_iter().map(|m| m * power).find(|&step| step >= rough).unwrap()
+}
+
+/// Formats the value of a tick with as many digits as `step` needs
+fn format_tick(value: f64, step: f64) -> String {
+    if value == 0.0 {
+        return String::from("0");
+    }
+    let magnitude = step.log10().floor() as i32;
+    if !(-4..5).contains(&magnitude) {
+        let digits = (value.abs().log10().floor() as i32 - magnitude).max(0) as usize;
+        format!("{:.*e}", digits, value)
+    } else {
+        format!("{:.*}", (-magnitude).max(0) as usize, value)
+    }
+}
+
+/// The rows of the glyph of `c`, 3 bits each, the most significant bit on the left. Only digits, what numbers are
+/// written with, `=`, `_` and uppercase letters have one
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'e' => [0b000, 0b111, 0b111, 0b100, 0b111],
+        'i' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        _ => [0; 5],
+    }
+}
+
+/// The size in pixels of `text`, with its background
+pub fn text_size(text: &str, scale: usize) -> (isize, isize) {
+    let chars = text.chars().count();
+    (((chars * 4 + 1) * scale) as isize, (7 * scale) as isize)
+}
+
+/// Writes `text` in white on a black box whose top-left corner is at `at`
+pub fn draw_text(image: &mut Rgb32FImage, at: (isize, isize), text: &str, scale: usize) {
+    let (width, height) = text_size(text, scale);
+    for y in at.1..at.1 + height {
+        for x in at.0..at.0 + width {
+            put(image, x, y, BLACK);
+        }
+    }
+
+    let scale = scale as isize;
+    for (i, c) in text.chars().enumerate() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits >> (2 - column) & 1 == 0 {
+                    continue;
+                }
+                let (x, y) = (at.0 + (i as isize * 4 + 1 + column) * scale, at.1 + (row as isize + 1) * scale);
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        put(image, x + dx, y + dy, WHITE);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws a filled disk of radius `radius` around `center`
+pub fn draw_disk(image: &mut Rgb32FImage, center: (f64, f64), radius: f64, color: Rgb<f32>) {
+    let extent = radius.ceil() as isize;
+    let (cx, cy) = (center.0.round() as isize, center.1.round() as isize);
+    for y in cy - extent..=cy + extent {
+        for x in cx - extent..=cx + extent {
+            if (x as f64 - center.0).hypot(y as f64 - center.1) <= radius {
+                put(image, x, y, color);
+            }
+        }
+    }
+}
+
+/// Draws a diagonal cross of half-width `size` around `center`, with a black outline
+pub fn draw_cross(image: &mut Rgb32FImage, center: (f64, f64), size: f64, color: Rgb<f32>) {
+    let (x, y) = center;
+    draw_polyline(image, &[(x - size, y - size), (x + size, y + size)], color);
+    draw_polyline(image, &[(x - size, y + size), (x + size, y - size)], color);
+}
+
+fn put(image: &mut Rgb32FImage, x: isize, y: isize, color: Rgb<f32>) {
+    if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+/// Clips the segment between `a` and `b` to the rectangle `[0, width] x [0, height]` (Liang-Barsky),
+/// returning `None` if it lies fully outside
+fn clip(a: (f64, f64), b: (f64, f64), width: f64, height: f64) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, a.0), (dx, width - a.0), (-dy, a.1), (dy, height - a.1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some(((a.0 + t0 * dx, a.1 + t0 * dy), (a.0 + t1 * dx, a.1 + t1 * dy)))
+}
+
+/// Draws a one pixel wide line between `a` and `b`
+pub fn draw_line(image: &mut Rgb32FImage, a: (f64, f64), b: (f64, f64), color: Rgb<f32>) {
+    if ![a.0, a.1, b.0, b.1].iter().all(|x| x.is_finite()) {
+        return;
+    }
+    let Some((a, b)) = clip(a, b, image.width() as f64, image.height() as f64) else {
+        return;
+    };
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        put(image, (a.0 + t * (b.0 - a.0)).round() as isize, (a.1 + t * (b.1 - a.1)).round() as isize, color);
+    }
+}
+
+/// Draws a line through all of `points`, with a black outline so that it stands out on any basin
+pub fn draw_polyline(image: &mut Rgb32FImage, points: &[(f64, f64)], color: Rgb<f32>) {
+    for (dx, dy) in [(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)] {
+        for pair in points.windows(2) {
+            draw_line(image, (pair[0].0 + dx, pair[0].1 + dy), (pair[1].0 + dx, pair[1].1 + dy), BLACK);
+        }
+    }
+    for pair in points.windows(2) {
+        draw_line(image, pair[0], pair[1], color);
+    }
+}
+
+/// Draws a ring of radius `radius` around `center`, in `color` with a black outline
+pub fn draw_ring(image: &mut Rgb32FImage, center: (f64, f64), radius: f64, color: Rgb<f32>) {
+    let extent = radius.ceil() as isize + 2;
+    let (cx, cy) = (center.0.round() as isize, center.1.round() as isize);
+    for y in cy - extent..=cy + extent {
+        for x in cx - extent..=cx + extent {
+            let distance = ((x as f64 - center.0).hypot(y as f64 - center.1) - radius).abs();
+            if distance < 1.0 {
+                put(image, x, y, color);
+            } else if distance < 2.0 {
+                put(image, x, y, BLACK);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ticks() {
+    assert_eq!(tick_step(8.0), 1.0);
+    assert_eq!(tick_step(3.0), 0.5);
+    assert!((tick_step(0.0013) - 0.0002).abs() < 1e-15);
+    assert_eq!(format_tick(-1.5, 0.5), "-1.5");
+    assert_eq!(format_tick(2.0, 1.0), "2");
+    assert_eq!(format_tick(0.0, 1e-7), "0");
+    assert_eq!(format_tick(3.2e-6, 2e-7), "3.2e-6");
+    assert_eq!(format_tick(1234.0, 20.0), "1234");
+    assert_eq!(Overlay::parse_list("roots, axes"), Some(vec![Overlay::Roots, Overlay::Axes]));
+    assert_eq!(Overlay::parse_list("all").map(|list| list.len()), Some(5));
+    assert_eq!(Overlay::parse_list("roots,grid"), None);
+}
+
+#[test]
+fn test_clip() {
+    assert_eq!(clip((-10.0, 5.0), (20.0, 5.0), 10.0, 10.0), Some(((0.0, 5.0), (10.0, 5.0))));
+    assert_eq!(clip((2.0, 3.0), (4.0, 5.0), 10.0, 10.0), Some(((2.0, 3.0), (4.0, 5.0))));
+    assert_eq!(clip((-5.0, -5.0), (-1.0, 20.0), 10.0, 10.0), None);
+    assert_eq!(clip((1e6, 4.0), (-1e6, 4.0), 10.0, 10.0), Some(((10.0, 4.0), (0.0, 4.0))));
+
+    let mut image = Rgb32FImage::new(10, 10);
+    draw_line(&mut image, (-100.0, 2.0), (1e12, 2.0), WHITE);
+    assert!((0..10).all(|x| *image.get_pixel(x, 2) == WHITE));
+    assert_eq!(*image.get_pixel(0, 3), BLACK);
+}
@@ -1,7 +1,15 @@
 use num::complex::Complex;
 use std::fmt;
 use super::complex_simd::Complex8;
+use super::double_double::{ComplexDD, to_complex_dd};
 
+/// Relative size under which coefficients are considered to be zero by `Polynomial::gcd`
+pub const GCD_TOLERANCE: f64 = 1e-9;
+
+/// A polynomial with complex coefficients, stored from the constant term up
+///
+/// Trailing zero coefficients do not change the polynomial: `[1, 2]` and `[1, 2, 0]` are equal,
+/// and the zero polynomial can be stored either with zeros only or without any coefficient.
 #[derive(Clone, Debug)]
 pub struct Polynomial {
     params: Vec<Complex<f64>>,
@@ -51,10 +59,21 @@ impl Polynomial {
         }
     }
 
+    /// The coefficients, from the constant term up
+    pub fn coefficients(&self) -> &[Complex<f64>] {
+        &self.params
+    }
+
+    /// The number of stored coefficients, including trailing zeros
     pub fn len(&self) -> usize {
         self.params.len()
     }
 
+    pub fn is_zero(&self) -> bool {
+        self.params.iter().all(|x| x.re == 0.0 && x.im == 0.0)
+    }
+
+    /// The degree: the index of the highest non-zero coefficient, 0 for constants and for the zero polynomial
     pub fn dim(&self) -> usize {
         let mut res: usize = 0;
         for (i, x) in self.params.iter().enumerate() {
@@ -65,42 +84,248 @@ impl Polynomial {
         res
     }
 
+    /// Removes the trailing zero coefficients, returning the degree; the zero polynomial is left without coefficients
     pub fn shrink(&mut self) -> usize {
-        let dim = self.dim();
-        self.params.truncate(dim);
+        while self.params.last().is_some_and(|x| x.re == 0.0 && x.im == 0.0) {
+            self.params.pop();
+        }
         self.params.shrink_to_fit();
-        dim
+        self.dim()
+    }
+
+    /// The coefficient of the highest power of `z`, or zero for the zero polynomial
+    pub fn leading(&self) -> Complex<f64> {
+        self.params.get(self.dim()).copied().unwrap_or(Complex::new(0.0, 0.0))
+    }
+
+    /// Euclidean division: returns `(q, r)` such that `self = q * divisor + r`, with `r` of lower degree than `divisor`
+    ///
+    /// Panics if `divisor` is the zero polynomial.
+    pub fn div_rem(&self, divisor: &Polynomial) -> (Polynomial, Polynomial) {
+        assert!(!divisor.is_zero(), "division by the zero polynomial");
+        let (n, d) = (self.dim(), divisor.dim());
+        let leading = divisor.leading();
+
+        let mut remainder = self.params[..self.params.len().min(n + 1)].to_vec();
+        if n < d || self.is_zero() {
+            return (Polynomial::new(&[]), Polynomial { params: remainder });
+        }
+
+        let mut quotient = vec![Complex::new(0.0, 0.0); n - d + 1];
+        for i in (0..=n - d).rev() {
+            let factor = remainder[i + d] / leading;
+            quotient[i] = factor;
+            for (j, &x) in divisor.params[..=d].iter().enumerate() {
+                remainder[i + j] -= factor * x;
+            }
+            // Cancelled exactly, rather than left with rounding errors
+            remainder[i + d] = Complex::new(0.0, 0.0);
+        }
+
+        let mut remainder = Polynomial { params: remainder };
+        remainder.shrink();
+        (Polynomial { params: quotient }, remainder)
+    }
+
+    /// The composition `self(q(z))`
+    pub fn compose(&self, q: &Polynomial) -> Polynomial {
+        let mut res = Polynomial::new(&[]);
+
+        for &x in self.params.iter().rev() {
+            res = &(&res * q) + &Polynomial::constant(x);
+        }
+
+        res
+    }
+
+    /// `self` raised to the power `n`; the 0th power of any polynomial is `1`
+    pub fn pow(&self, mut n: u32) -> Polynomial {
+        let mut res = Polynomial::constant(1.0);
+        let mut base = self.clone();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                res *= &base;
+            }
+            base = &base * &base;
+            n >>= 1;
+        }
+
+        res
+    }
+
+    /// The antiderivative whose constant term is zero
+    pub fn antiderivative(&self) -> Polynomial {
+        let mut params = Vec::with_capacity(self.params.len() + 1);
+        params.push(Complex::new(0.0, 0.0));
+        for (i, x) in self.params.iter().enumerate() {
+            params.push(x / (i + 1) as f64);
+        }
+        Polynomial { params }
+    }
+
+    /// Returns the same polynomial divided by its leading coefficient
+    pub fn monic(&self) -> Polynomial {
+        let mut res = self.clone();
+        res.shrink();
+        match res.params.last().copied() {
+            Some(leading) => &res / leading,
+            None => res,
+        }
     }
 
+    /// The monic greatest common divisor, computed with Euclid's algorithm
+    ///
+    /// Since the coefficients are floats, remainders are considered to be zero once all of their coefficients
+    /// are below `GCD_TOLERANCE` times the largest coefficient of the inputs. The GCD of a polynomial and of its
+    /// derivative has the repeated roots of the polynomial as roots, each with its multiplicity minus one.
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let (mut a, mut b) = (self.monic(), other.monic());
+        let scale = a.params.iter().chain(b.params.iter()).map(|x| x.norm()).fold(0.0, f64::max);
+        let negligible = |p: &Polynomial| p.params.iter().all(|x| x.norm() <= GCD_TOLERANCE * scale);
+
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b);
+            a = b;
+            b = if negligible(&r) { Polynomial::new(&[]) } else { r.monic() };
+        }
+        a
+    }
+
+    /// How many times `root` is a root, within the tolerance of `gcd`; 0 if it is not a root
+    pub fn root_multiplicity(&self, root: Complex<f64>) -> usize {
+        let factor = Polynomial::new(&[-root, Complex::new(1.0, 0.0)]);
+        let mut p = self.monic();
+        let mut res = 0;
+
+        while p.dim() > 0 {
+            let scale = p.params.iter().map(|x| x.norm()).fold(0.0, f64::max);
+            let (quotient, remainder) = p.div_rem(&factor);
+            if remainder.leading().norm() > GCD_TOLERANCE * scale {
+                break;
+            }
+            res += 1;
+            p = quotient;
+        }
+        res
+    }
+
+    /// Finds all the complex roots, repeated according to their multiplicity, with the Aberth-Ehrlich method
+    ///
+    /// Repeated roots are only found to about `1 / multiplicity` of the digits of an `f64`.
+    pub fn roots(&self) -> Vec<Complex<f64>> {
+        let p = self.monic();
+        let n = p.dim();
+        if n == 0 {
+            return vec![];
+        }
+
+        // All roots lie in the disk of radius 2 max |a_(n - k)|^(1 / k) (Fujiwara's bound); start on a spiral
+        // inside it, avoiding symmetric starting points that would stay stuck on the real axis
+        let radius = (1..=n).map(|k| p.params[n - k].norm().powf(1.0 / k as f64)).fold(0.0, f64::max) * 2.0;
+        let seed = Complex::from_polar(1.0, 2.4);
+        let mut roots = (0..n).map(|k| seed.powu(k as u32) * radius * (k + 1) as f64 / n as f64).collect::<Vec<_>>();
+
+        for _ in 0..1000 {
+            let mut change: f64 = 0.0;
+            for i in 0..n {
+                let [f, df] = p.eval_with_derivs::<2>(roots[i]);
+                let ratio = f / df;
+                let repulsion = (0..n).filter(|&j| j != i).map(|j| 1.0 / (roots[i] - roots[j])).sum::<Complex<f64>>();
+                let step = ratio / (1.0 - ratio * repulsion);
+                if step.is_finite() {
+                    roots[i] -= step;
+                    change = change.max(step.norm() / roots[i].norm().max(f64::MIN_POSITIVE));
+                }
+            }
+            if change < 1e-15 {
+                break;
+            }
+        }
+        roots
+    }
+
+    /// Evaluates the polynomial at `z`, using Horner's method
     pub fn eval(&self, z: Complex<f64>) -> Complex<f64> {
-        let mut acc = Complex::new(1.0, 0.0);
         let mut res = Complex::new(0.0, 0.0);
 
-        for &x in self.params.iter() {
-            res += acc * x;
-            acc *= z;
+        for &x in self.params.iter().rev() {
+            res = res * z + x;
         }
 
         res
     }
 
     pub fn eval8(&self, z: Complex8) -> Complex8 {
-        let mut acc = Complex8::from_complex(Complex::new(1.0, 0.0));
         let mut res = Complex8::from_complex(Complex::new(0.0, 0.0));
 
-        for &x in self.params.iter() {
-            res += acc * x;
-            acc *= z;
+        for &x in self.params.iter().rev() {
+            res = res * z + x;
+        }
+
+        res
+    }
+
+    /// Returns the values at `z` of the polynomial and of its first `N - 1` derivatives, in a single pass:
+    /// `eval_with_derivs::<3>(z)` gives `[f(z), f'(z), f''(z)]`
+    pub fn eval_with_derivs<const N: usize>(&self, z: Complex<f64>) -> [Complex<f64>; N] {
+        const { assert!(N > 0, "eval_with_derivs needs at least the value") };
+        let mut res = [Complex::new(0.0, 0.0); N];
+
+        for &x in self.params.iter().rev() {
+            for k in (1..N).rev() {
+                res[k] = res[k] * z + res[k - 1];
+            }
+            res[0] = res[0] * z + x;
         }
 
+        // Horner's method gives the Taylor coefficients, f^(k)(z) / k!
+        let mut factorial = 1.0;
+        for (k, value) in res.iter_mut().enumerate().skip(2) {
+            factorial *= k as f64;
+            *value *= factorial;
+        }
         res
     }
 
+    /// Same as `eval_with_derivs`, on 8 values at once
+    pub fn eval_with_derivs8<const N: usize>(&self, z: Complex8) -> [Complex8; N] {
+        const { assert!(N > 0, "eval_with_derivs8 needs at least the value") };
+        let mut res = [Complex8::from_complex(Complex::new(0.0, 0.0)); N];
+
+        for &x in self.params.iter().rev() {
+            for k in (1..N).rev() {
+                let previous = res[k - 1];
+                res[k] *= z;
+                res[k] += previous;
+            }
+            res[0] *= z;
+            res[0] += x;
+        }
+
+        let mut factorial = 1.0;
+        for (k, value) in res.iter_mut().enumerate().skip(2) {
+            factorial *= k as f64;
+            *value *= factorial;
+        }
+        res
+    }
+
+    /// Same as `eval`, in double-double precision
+    pub fn eval_dd(&self, z: ComplexDD) -> ComplexDD {
+        let mut res = to_complex_dd(Complex::new(0.0, 0.0));
+
+        for &x in self.params.iter().rev() {
+            res = res * z + to_complex_dd(x);
+        }
+
+        res
+    }
+
+    /// The derivative; that of a constant is the zero constant
     pub fn diff(&self) -> Polynomial {
         if self.params.len() <= 1 {
-            return Polynomial {
-                params: vec![]
-            };
+            return Polynomial::constant(0.0);
         }
 
         let mut res = Polynomial {
@@ -160,7 +385,12 @@ impl std::ops::Add<&Polynomial> for &Polynomial {
 impl std::ops::Mul<&Polynomial> for &Polynomial {
     type Output = Polynomial;
 
+    /// The product; if either polynomial has no coefficients, so does the product
     fn mul(self, q: &Polynomial) -> Polynomial {
+        if self.params.is_empty() || q.params.is_empty() {
+            return Polynomial::new(&[]);
+        }
+
         let mut res = Polynomial {
             params: vec![Complex::new(0.0, 0.0); self.params.len() + q.params.len() - 1],
         };
@@ -184,19 +414,100 @@ impl std::ops::MulAssign<&Polynomial> for Polynomial {
     }
 }
 
-impl std::cmp::PartialEq for Polynomial {
-    fn eq(&self, other: &Self) -> bool {
-        if self.dim() != other.dim() {
-            return false;
+impl std::ops::SubAssign<&Polynomial> for Polynomial {
+    fn sub_assign(&mut self, q: &Polynomial) {
+        if q.params.len() > self.params.len() {
+            self.params.resize(q.params.len(), Complex::new(0.0, 0.0));
+        }
+        for (x, y) in self.params.iter_mut().zip(q.params.iter()) {
+            *x -= y;
         }
+    }
+}
 
-        for (i, x) in self.params.iter().copied().enumerate() {
-            if other.params[i] != x {
-                return false;
-            }
+impl std::ops::Sub<&Polynomial> for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(mut self, q: &Polynomial) -> Self {
+        self -= q;
+        self
+    }
+}
+
+impl std::ops::Sub<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, q: &Polynomial) -> Polynomial {
+        let mut res = self.clone();
+        res -= q;
+        res
+    }
+}
+
+impl std::ops::Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(mut self) -> Polynomial {
+        for x in self.params.iter_mut() {
+            *x = -*x;
         }
+        self
+    }
+}
 
-        true
+impl std::ops::Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        -self.clone()
+    }
+}
+
+// Polynomial * scalar
+
+impl std::ops::MulAssign<Complex<f64>> for Polynomial {
+    fn mul_assign(&mut self, k: Complex<f64>) {
+        for x in self.params.iter_mut() {
+            *x *= k;
+        }
+    }
+}
+
+impl std::ops::Mul<Complex<f64>> for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, k: Complex<f64>) -> Polynomial {
+        let mut res = self.clone();
+        res *= k;
+        res
+    }
+}
+
+impl std::ops::DivAssign<Complex<f64>> for Polynomial {
+    fn div_assign(&mut self, k: Complex<f64>) {
+        for x in self.params.iter_mut() {
+            *x /= k;
+        }
+    }
+}
+
+impl std::ops::Div<Complex<f64>> for &Polynomial {
+    type Output = Polynomial;
+
+    fn div(self, k: Complex<f64>) -> Polynomial {
+        let mut res = self.clone();
+        res /= k;
+        res
+    }
+}
+
+/// Exact equality of the coefficients, missing ones being zero
+impl std::cmp::PartialEq for Polynomial {
+    fn eq(&self, other: &Self) -> bool {
+        let zero = Complex::new(0.0, 0.0);
+        (0..self.params.len().max(other.params.len())).all(|i| {
+            self.params.get(i).unwrap_or(&zero) == other.params.get(i).unwrap_or(&zero)
+        })
     }
 }
 
@@ -238,3 +549,237 @@ fn test_polynomial_from_roots() {
         Complex::new(1.0, 0.0),
     ]));
 }
+
+#[test]
+fn test_polynomial_edge_cases() {
+    let c = |re: f64| Complex::new(re, 0.0);
+
+    let mut p = Polynomial::new(&[c(1.0), c(2.0), c(3.0), c(0.0), c(0.0)]);
+    assert_eq!(p.shrink(), 2);
+    assert_eq!(p.coefficients(), &[c(1.0), c(2.0), c(3.0)]);
+    let mut zero = Polynomial::new(&[c(0.0), c(0.0)]);
+    assert_eq!(zero.shrink(), 0);
+    assert_eq!(zero.len(), 0);
+    assert!(zero.is_zero());
+
+    assert_eq!(p, Polynomial::new(&[c(1.0), c(2.0), c(3.0), c(0.0)]));
+    assert_eq!(Polynomial::new(&[c(1.0), c(2.0), c(3.0), c(0.0)]), p);
+    assert!(p != Polynomial::new(&[c(1.0), c(2.0)]));
+    assert!(Polynomial::new(&[c(1.0), c(2.0)]) != p);
+    assert_eq!(Polynomial::new(&[]), Polynomial::constant(0.0));
+
+    let empty = Polynomial::new(&[]);
+    assert_eq!((&p * &empty).len(), 0);
+    assert_eq!((&empty * &p).len(), 0);
+    assert_eq!(&p * &Polynomial::constant(2.0), Polynomial::new(&[c(2.0), c(4.0), c(6.0)]));
+    assert_eq!((&p * &p).dim(), 4);
+
+    assert_eq!(Polynomial::constant(5.0).diff(), Polynomial::constant(0.0));
+    assert_eq!(Polynomial::constant(5.0).diff().len(), 1);
+    assert_eq!(p.diff(), Polynomial::new(&[c(2.0), c(6.0)]));
+}
+
+#[test]
+fn test_polynomial_algebra() {
+    let c = |re: f64| Complex::new(re, 0.0);
+    let roots = |roots: &[f64]| Polynomial::from_roots(&roots.iter().map(|&r| c(r)).collect::<Vec<_>>());
+    let p = Polynomial::new(&[c(1.0), c(-2.0), c(3.0)]);
+    let q = Polynomial::new(&[c(0.0), c(1.0)]);
+
+    assert_eq!(&p - &p, Polynomial::new(&[]));
+    assert_eq!(-&p, Polynomial::new(&[c(-1.0), c(2.0), c(-3.0)]));
+    assert_eq!(&q - &p, -(&p - &q));
+    assert_eq!(&p * Complex::new(0.0, 1.0), Polynomial::new(&[Complex::new(0.0, 1.0), Complex::new(0.0, -2.0), Complex::new(0.0, 3.0)]));
+    assert_eq!(&p / c(2.0), Polynomial::new(&[c(0.5), c(-1.0), c(1.5)]));
+
+    // (z - 1)(z - 2) composed with z + 1 is z(z - 1)
+    let shift = Polynomial::new(&[c(1.0), c(1.0)]);
+    assert_eq!(roots(&[1.0, 2.0]).compose(&shift), roots(&[0.0, 1.0]));
+    assert_eq!(q.pow(0), Polynomial::constant(1.0));
+    assert_eq!(shift.pow(3), Polynomial::new(&[c(1.0), c(3.0), c(3.0), c(1.0)]));
+    assert_eq!(p.antiderivative().diff(), p);
+    assert_eq!(p.antiderivative().coefficients()[0], c(0.0));
+
+    let (quotient, remainder) = roots(&[1.0, 2.0, 3.0]).div_rem(&roots(&[2.0]));
+    assert_eq!(quotient, roots(&[1.0, 3.0]));
+    assert!(remainder.is_zero());
+    let (quotient, remainder) = p.div_rem(&Polynomial::new(&[c(1.0), c(1.0)]));
+    assert_eq!(&(&quotient * &Polynomial::new(&[c(1.0), c(1.0)])) + &remainder, p);
+    assert_eq!(remainder.dim(), 0);
+    assert_eq!(q.div_rem(&p), (Polynomial::new(&[]), q.clone()));
+
+    // Repeated roots
+    let f = roots(&[1.0, 1.0, 1.0, -2.0, 0.5, 0.5]);
+    assert_close(&f.gcd(&f.diff()), &roots(&[1.0, 1.0, 0.5]));
+    assert_eq!(roots(&[1.0, 2.0]).gcd(&roots(&[3.0])), Polynomial::constant(1.0));
+    assert_eq!(p.gcd(&Polynomial::new(&[])), p.monic());
+    assert_eq!(f.root_multiplicity(c(1.0)), 3);
+    assert_eq!(f.root_multiplicity(c(-2.0)), 1);
+    assert_eq!(f.root_multiplicity(c(2.0)), 0);
+
+    // Root finding
+    let expected = [c(1.0), Complex::new(-0.5, 2.0), Complex::new(-0.5, -2.0), c(3.0), Complex::new(0.0, 0.25)];
+    let mut found = Polynomial::from_roots(&expected).roots();
+    assert_eq!(found.len(), expected.len());
+    for root in expected.iter() {
+        let closest = (0..found.len()).min_by(|&i, &j| (found[i] - root).norm().total_cmp(&(found[j] - root).norm())).unwrap();
+        assert!((found.swap_remove(closest) - root).norm() < 1e-12);
+    }
+    let found = f.roots();
+    assert_eq!(found.len(), 6);
+    assert!(found.iter().all(|z| [1.0, -2.0, 0.5].iter().any(|&root| (z - c(root)).norm() < 1e-4)));
+    assert!(Polynomial::constant(2.0).roots().is_empty());
+}
+
+/// A small xorshift generator, for the property tests
+#[cfg(test)]
+struct TestRng(u64);
+
+#[cfg(test)]
+impl TestRng {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+
+    fn complex(&mut self) -> Complex<f64> {
+        Complex::new(self.next(), self.next())
+    }
+
+    fn polynomial(&mut self) -> Polynomial {
+        let len = ((self.next() + 1.0) * 4.0) as usize + 1;
+        Polynomial { params: (0..len).map(|_| self.complex()).collect() }
+    }
+}
+
+#[cfg(test)]
+fn assert_close(p: &Polynomial, q: &Polynomial) {
+    let zero = Complex::new(0.0, 0.0);
+    for i in 0..p.len().max(q.len()) {
+        let (a, b) = (p.params.get(i).unwrap_or(&zero), q.params.get(i).unwrap_or(&zero));
+        assert!((a - b).norm() < 1e-9, "{} != {}", p, q);
+    }
+}
+
+#[test]
+fn test_polynomial_properties() {
+    let mut rng = TestRng(0x9e3779b97f4a7c15);
+
+    for _ in 0..200 {
+        let (p, q, r) = (rng.polynomial(), rng.polynomial(), rng.polynomial());
+
+        assert_eq!(&p + &q, &q + &p);
+        assert_close(&(&p * &q), &(&q * &p));
+        assert_close(&(&p * &(&q + &r)), &(&(&p * &q) + &(&p * &r)));
+        // Product rule
+        assert_close(&(&p * &q).diff(), &(&(&p.diff() * &q) + &(&p * &q.diff())));
+
+        assert_close(&(&(&p - &q) + &q), &p);
+        let (quotient, remainder) = p.div_rem(&q);
+        assert_close(&(&(&quotient * &q) + &remainder), &p);
+        assert!(remainder.dim() < q.dim() || q.dim() == 0);
+        assert_close(&p.antiderivative().diff(), &p);
+
+        let z = rng.complex();
+        assert!((p.compose(&q).eval(z) - p.eval(q.eval(z))).norm() < 1e-9);
+        assert!(((&p * &q).eval(z) - p.eval(z) * q.eval(z)).norm() < 1e-9);
+
+        let roots = (0..((rng.next() + 1.0) * 4.0) as usize + 1).map(|_| rng.complex() * 2.0).collect::<Vec<_>>();
+        let f = Polynomial::from_roots(&roots);
+        assert_eq!(f.dim(), roots.len());
+        for &root in roots.iter() {
+            assert!(f.eval(root).norm() < 1e-9, "f({}) = {}", root, f.eval(root));
+        }
+    }
+}
+
+#[test]
+fn test_eval_with_derivs() {
+    let f = Polynomial::from_roots(&[
+        Complex::new(1.0, 0.0),
+        Complex::new(-0.5, 2.0),
+        Complex::new(0.25, -1.0),
+        Complex::new(3.0, 0.5),
+    ]);
+    let (df, ddf, dddf) = (f.diff(), f.diff().diff(), f.diff().diff().diff());
+    let close = |a: Complex<f64>, b: Complex<f64>| (a - b).norm() < 1e-9 * b.norm().max(1.0);
+
+    let z = Complex::new(0.7, -1.3);
+    let [v, dv, ddv, dddv] = f.eval_with_derivs::<4>(z);
+    assert!(close(v, f.eval(z)));
+    assert!(close(dv, df.eval(z)));
+    assert!(close(ddv, ddf.eval(z)));
+    assert!(close(dddv, dddf.eval(z)));
+    assert!(close(f.eval(Complex::new(1.0, 0.0)), Complex::new(0.0, 0.0)));
+
+    let zs: [Complex<f64>; 8] = std::array::from_fn(|i| Complex::new(i as f64 * 0.3 - 1.0, 0.5 - i as f64 * 0.2));
+    let [values, derivatives] = f.eval_with_derivs8::<2>(Complex8::from(zs));
+    let (values, derivatives): ([Complex<f64>; 8], [Complex<f64>; 8]) = (values.into(), derivatives.into());
+    for i in 0..8 {
+        assert!(close(values[i], f.eval(zs[i])));
+        assert!(close(derivatives[i], df.eval(zs[i])));
+    }
+}
+
+/// The previous evaluation, which keeps a running power of `z`, to compare against Horner's method
+#[cfg(test)]
+fn eval_with_powers(p: &Polynomial, z: Complex<f64>) -> Complex<f64> {
+    let mut acc = Complex::new(1.0, 0.0);
+    let mut res = Complex::new(0.0, 0.0);
+    for &x in p.params.iter() {
+        res += acc * x;
+        acc *= z;
+    }
+    res
+}
+
+#[cfg(test)]
+fn bench_polynomial() -> Polynomial {
+    let roots = (0..12)
+        .map(|i| Complex::from_polar(1.0 + i as f64 * 0.1, i as f64 * 0.7))
+        .collect::<Vec<_>>();
+    Polynomial::from_roots(&roots)
+}
+
+#[bench]
+fn bench_eval_powers(b: &mut test::Bencher) {
+    let (f, df) = (bench_polynomial(), bench_polynomial().diff());
+    let z = test::black_box(Complex::new(0.3, 0.4));
+    b.iter(|| eval_with_powers(&f, z) / eval_with_powers(&df, z));
+}
+
+#[bench]
+fn bench_eval_horner(b: &mut test::Bencher) {
+    let (f, df) = (bench_polynomial(), bench_polynomial().diff());
+    let z = test::black_box(Complex::new(0.3, 0.4));
+    b.iter(|| f.eval(z) / df.eval(z));
+}
+
+#[bench]
+fn bench_eval_with_derivs(b: &mut test::Bencher) {
+    let f = bench_polynomial();
+    let z = test::black_box(Complex::new(0.3, 0.4));
+    b.iter(|| {
+        let [v, dv] = f.eval_with_derivs::<2>(z);
+        v / dv
+    });
+}
+
+#[bench]
+fn bench_eval8_horner(b: &mut test::Bencher) {
+    let (f, df) = (bench_polynomial(), bench_polynomial().diff());
+    let z = test::black_box(Complex8::from_complex(Complex::new(0.3, 0.4)));
+    b.iter(|| f.eval8(z) / df.eval8(z));
+}
+
+#[bench]
+fn bench_eval_with_derivs8(b: &mut test::Bencher) {
+    let f = bench_polynomial();
+    let z = test::black_box(Complex8::from_complex(Complex::new(0.3, 0.4)));
+    b.iter(|| {
+        let [v, dv] = f.eval_with_derivs8::<2>(z);
+        v / dv
+    });
+}
@@ -0,0 +1,135 @@
+//! Progress reporting and per-stage timings
+
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+
+/// A progress bar with an ETA, printed to stderr; it can be shared between threads
+pub struct ProgressBar {
+    label: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    start: Instant,
+}
+
+impl ProgressBar {
+    pub fn new(label: &'static str, total: usize) -> Self {
+        Self {
+            label,
+            total,
+            done: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// Marks one more unit of work as done, redrawing the bar every percent
+    pub fn inc(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if done == self.total || done * 100 / self.total != (done - 1) * 100 / self.total {
+            let mut stderr = std::io::stderr().lock();
+            let _ = write!(stderr, "\r{}", self.render(done, self.start.elapsed()));
+            if done == self.total {
+                let _ = writeln!(stderr);
+            }
+            let _ = stderr.flush();
+        }
+    }
+
+    fn render(&self, done: usize, elapsed: Duration) -> String {
+        let filled = done * BAR_WIDTH / self.total.max(1);
+        let eta = if done > 0 {
+            elapsed.mul_f64((self.total - done) as f64 / done as f64)
+        } else {
+            Duration::ZERO
+        };
+
+        format!(
+            "{} [{}{}] {:3}% ({}/{}) ETA {}",
+            self.label,
+            "#".repeat(filled),
+            ".".repeat(BAR_WIDTH - filled),
+            done * 100 / self.total.max(1),
+            done,
+            self.total,
+            format_duration(eta),
+        )
+    }
+}
+
+/// Wall-clock time spent in each stage of the rendering of a frame
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f`, recording the time it took under the name `stage`
+    pub fn time<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.stages.push((stage, start.elapsed()));
+        res
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// A one-line summary, like `newton 12.1s, distance 0.3s, ..., total 14.0s`
+    pub fn summary(&self) -> String {
+        let mut res = String::new();
+        for (stage, duration) in self.stages.iter() {
+            res += &format!("{} {:.1}s, ", stage, duration.as_secs_f64());
+        }
+        res + &format!("total {:.1}s", self.total().as_secs_f64())
+    }
+
+    /// The timings as a JSON object, mapping each stage to its duration in seconds
+    pub fn to_json(&self) -> String {
+        let mut fields = self.stages.iter()
+            .map(|(stage, duration)| format!("\"{}\": {:.6}", stage, duration.as_secs_f64()))
+            .collect::<Vec<_>>();
+        fields.push(format!("\"total\": {:.6}", self.total().as_secs_f64()));
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[test]
+fn test_progress_render() {
+    let bar = ProgressBar::new("Newton", 200);
+    assert_eq!(
+        bar.render(50, Duration::from_secs(30)),
+        format!("Newton [{}{}]  25% (50/200) ETA 1m30s", "#".repeat(7), ".".repeat(23))
+    );
+    assert_eq!(format_duration(Duration::from_secs(3 * 3600 + 62)), "3h01m02s");
+}
+
+#[test]
+fn test_timings() {
+    let mut timings = Timings::new();
+    assert_eq!(timings.time("newton", || 4), 4);
+    timings.time("draw", || ());
+
+    let json = timings.to_json();
+    assert!(json.starts_with("{\"newton\": "));
+    assert!(json.contains(", \"draw\": "));
+    assert!(json.contains(", \"total\": "));
+    assert!(timings.summary().starts_with("newton 0.0s, draw 0.0s, total"));
+}
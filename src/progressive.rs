@@ -0,0 +1,162 @@
+//! Progressive rendering: the Newton-Raphson pass is first run on every 16th pixel, then refined to every 8th,
+//! 4th, 2nd and finally every pixel, reusing the samples computed by the previous passes.
+//!
+//! When the four corners of a block of the previous pass all converged to the same root, the pixels inside the block
+//! are not computed, but filled with the sample of its top-left corner, whose distance to the boundary is left out
+//! to be propagated from the computed pixels (see `render.rs`). This saves most of the work inside the basins,
+//! at the cost of missing the features smaller than a block that do not touch any of its corners.
+
+use std::sync::Mutex;
+use scoped_threadpool::Pool;
+use super::{PolyInfo, THREADS};
+use super::newton::{calc_pixel, Sample};
+use super::scene::Scene;
+
+/// Distance between the pixels computed by each pass
+pub const STEPS: [usize; 5] = [16, 8, 4, 2, 1];
+
+pub struct Progressive {
+    scene: Scene,
+    samples: Vec<Sample>,
+    /// Whether each sample was set by a previous pass, either computed or filled
+    known: Vec<bool>,
+    /// Index in `STEPS` of the next pass
+    pass: usize,
+    /// Number of pixels on which the Newton-Raphson method was actually run
+    computed: usize,
+}
+
+impl Progressive {
+    pub fn new(scene: &Scene) -> Self {
+        Self {
+            scene: scene.clone(),
+            samples: vec![Sample::default(); scene.width * scene.height],
+            known: vec![false; scene.width * scene.height],
+            pass: 0,
+            computed: 0,
+        }
+    }
+
+    /// The step of the last pass that was run, `None` before the first one
+    pub fn step(&self) -> Option<usize> {
+        self.pass.checked_sub(1).map(|pass| STEPS[pass])
+    }
+
+    pub fn computed(&self) -> usize {
+        self.computed
+    }
+
+    /// Runs the next pass; returns false if the image was already complete
+    pub fn refine(&mut self, info: &PolyInfo) -> bool {
+        let Some(&step) = STEPS.get(self.pass) else {
+            return false;
+        };
+        let (width, height) = (self.scene.width, self.scene.height);
+
+        let updates = Mutex::new(Vec::new());
+        let mut pool = Pool::new(THREADS);
+        let this = &*self;
+        pool.scoped(|scoped| {
+            for y in (0..height).filter(|&y| on_grid(y, step, height)) {
+                let updates = &updates;
+                scoped.execute(move || {
+                    let row = (0..width)
+                        .filter(|&x| on_grid(x, step, width) && !this.known[x + y * width])
+                        .map(|x| match this.fill(x, y, step, info) {
+                            Some(sample) => (x, sample, false),
+                            None => (x, calc_pixel(x, y, info, &this.scene), true),
+                        })
+                        .collect::<Vec<_>>();
+                    updates.lock().unwrap().push((y, row));
+                });
+            }
+        });
+
+        for (y, row) in updates.into_inner().unwrap() {
+            for (x, sample, computed) in row {
+                self.samples[x + y * width] = sample;
+                self.known[x + y * width] = true;
+                self.computed += computed as usize;
+            }
+        }
+        self.pass += 1;
+        true
+    }
+
+    /// Returns the sample to copy into `(x, y)` if the corners of its block in the previous pass
+    /// all converged to the same root
+    fn fill(&self, x: usize, y: usize, step: usize, info: &PolyInfo) -> Option<Sample> {
+        if step == STEPS[0] {
+            return None;
+        }
+        let (width, height) = (self.scene.width, self.scene.height);
+        let block = step * 2;
+        let (x0, y0) = (x - x % block, y - y % block);
+        let (x1, y1) = ((x0 + block).min(width - 1), (y0 + block).min(height - 1));
+
+        let corner = self.samples[x0 + y0 * width];
+        let same = [(x1, y0), (x0, y1), (x1, y1)].iter().all(|&(cx, cy)| {
+            debug_assert!(self.known[cx + cy * width]);
+            self.samples[cx + cy * width].root == corner.root
+        });
+
+        (same && corner.root < info.roots.len()).then_some(Sample { distance: None, ..corner })
+    }
+
+    /// The samples of the whole image, where each pixel that was not computed yet takes the value
+    /// of the nearest computed pixel above and to its left
+    pub fn samples(&self) -> Vec<Sample> {
+        let Some(step) = self.step() else {
+            return self.samples.clone();
+        };
+        let width = self.scene.width;
+
+        let mut res = self.samples.clone();
+        for y in 0..self.scene.height {
+            for x in 0..width {
+                if !self.known[x + y * width] {
+                    res[x + y * width] = self.samples[(x - x % step) + (y - y % step) * width];
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Whether row or column `i`, out of `len`, is computed by a pass of step `step`;
+/// the last one always is, so that every block has its four corners
+#[inline]
+fn on_grid(i: usize, step: usize, len: usize) -> bool {
+    i.is_multiple_of(step) || i == len - 1
+}
+
+#[test]
+fn test_progressive() {
+    use num::complex::Complex;
+    use super::render::compute_samples;
+
+    let scene = Scene {
+        width: 70,
+        height: 50,
+        scale: 2.0,
+        iterations: 200,
+        relaxation: 1.0,
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-0.5, 0.866), Complex::new(-0.5, -0.866)],
+        ..Scene::default()
+    };
+    let info = PolyInfo::new(&scene);
+
+    let mut renderer = Progressive::new(&scene);
+    assert!(renderer.refine(&info));
+    assert_eq!(renderer.step(), Some(16));
+    // Columns 0, 16, 32, 48, 64, 69 and rows 0, 16, 32, 48, 49
+    assert_eq!(renderer.computed(), 6 * 5);
+    while renderer.refine(&info) {}
+    assert_eq!(renderer.step(), Some(1));
+    assert!(renderer.computed() < scene.width * scene.height);
+
+    let brute = compute_samples(&info, &scene, false);
+    let progressive = renderer.samples();
+    let mismatches = brute.iter().zip(progressive.iter()).filter(|(a, b)| a.root != b.root).count();
+    assert!(mismatches * 100 < brute.len(), "{} mismatched pixels", mismatches);
+}
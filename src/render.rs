@@ -0,0 +1,291 @@
+//! The rendering pipeline: running the Newton-Raphson method over the whole image,
+//! then computing the buffers needed for shading
+//!
+//! The distance of each pixel to the boundary of its basin comes from the derivative of its orbit (see
+//! `newton::distance_estimate`), so it varies smoothly across the image instead of counting whole pixels. The pixels
+//! that were filled in rather than computed take the smallest distance through their neighbors, which also caps the
+//! estimate where it overshoots, around the preimages of the critical points of the Newton map.
+//!
+//! The shading was tuned at the default resolution, so its lengths, the distances fed to the proximity remap and the
+//! radius of the blur, are measured in pixels of that resolution: they are fixed fractions of the image, and a preview
+//! looks like the final render.
+
+use std::f64::consts::SQRT_2;
+use std::sync::{Arc, Mutex};
+use num::complex::Complex;
+use scoped_threadpool::Pool;
+use distance_transform::*;
+use super::{PolyInfo, THREADS, WIDTH, HEIGHT};
+use super::newton::{calc_row, Sample};
+use super::draw::gaussian_blur;
+use super::progress::{ProgressBar, Timings};
+use super::progressive::Progressive;
+use super::subdivide;
+use super::scene::Scene;
+
+/// Radius of the blur of the gradient of the proximity, in pixels of the default resolution
+const BLUR_RADIUS: f64 = 4.0;
+
+/// The intermediate buffers of a frame, from which the final image is drawn
+pub struct Layers {
+    pub width: usize,
+    pub height: usize,
+    /// Index of the root each pixel converged to, `roots.len()` if it did not converge
+    pub table: Vec<usize>,
+    pub iterations: Vec<usize>,
+    /// Final value of `z` for each pixel
+    pub values: Vec<Complex<f64>>,
+    /// Estimated distance to the boundary of the basin, in pixels
+    pub distance: GenericGrid<f64>,
+    pub proximity: GenericGrid<f64>,
+    pub nabla_dx: GenericGrid<f64>,
+    pub nabla_dy: GenericGrid<f64>,
+}
+
+/// How the Newton-Raphson pass visits the pixels of the image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Every pixel is computed
+    Brute,
+    /// Coarse-to-fine refinement, see `progressive.rs`
+    Progressive,
+    /// Mariani-Silver subdivision, see `subdivide.rs`
+    Subdivide,
+}
+
+impl Method {
+    pub const NAMES: &'static str = "brute, progressive, subdivide";
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "brute" => Some(Self::Brute),
+            "progressive" => Some(Self::Progressive),
+            "subdivide" => Some(Self::Subdivide),
+            _ => None,
+        }
+    }
+}
+
+/// Runs the Newton-Raphson method on every pixel and computes the buffers needed for shading
+///
+/// If `verbose` is set, the name of each stage and the progress of the Newton-Raphson pass are printed.
+pub fn compute_layers(poly_info: &PolyInfo, scene: &Scene, method: Method, timings: &mut Timings, verbose: bool) -> Layers {
+    if verbose {
+        println!("Running the Newton-Raphson algorithm...");
+    }
+    let (samples, computed) = timings.time("newton", || compute_samples_with(poly_info, scene, method, verbose));
+    if verbose && method != Method::Brute {
+        println!("Computed {} of {} pixels", computed, scene.width * scene.height);
+    }
+
+    layers_from_samples(&samples, scene, timings, verbose)
+}
+
+/// Runs the Newton-Raphson method over the image with `method`, returning the samples
+/// and the number of pixels that were actually computed
+pub fn compute_samples_with(poly_info: &PolyInfo, scene: &Scene, method: Method, verbose: bool) -> (Vec<Sample>, usize) {
+    match method {
+        Method::Brute => (compute_samples(poly_info, scene, verbose), scene.width * scene.height),
+        Method::Progressive => {
+            let mut renderer = Progressive::new(scene);
+            while renderer.refine(poly_info) {}
+            (renderer.samples(), renderer.computed())
+        }
+        Method::Subdivide => subdivide::compute_samples(poly_info, scene, verbose),
+    }
+}
+
+/// Runs the Newton-Raphson method on every pixel, in a thread pool
+pub fn compute_samples(poly_info: &PolyInfo, scene: &Scene, verbose: bool) -> Vec<Sample> {
+    let (width, height) = (scene.width, scene.height);
+    let samples = vec![Sample::default(); width * height];
+
+    let mut pool = Pool::new(THREADS);
+    let samples = Mutex::new(samples);
+    let progress = verbose.then(|| ProgressBar::new("Newton", height));
+    pool.scoped(|scoped| {
+        let samples = Arc::new(&samples);
+        let progress = &progress;
+        for y in 0..height {
+            let samples = Arc::clone(&samples);
+            scoped.execute(move || {
+                let mut local_samples = vec![Sample::default(); width];
+                calc_row(y, &mut local_samples, poly_info, scene);
+
+                match samples.lock() {
+                    Ok(mut lock) => {
+                        lock[(y * width)..((y + 1) * width)].copy_from_slice(&local_samples);
+                    }
+                    Err(e) => panic!("{}", e),
+                }
+                if let Some(progress) = progress {
+                    progress.inc();
+                }
+            });
+        }
+    });
+    samples.into_inner().unwrap()
+}
+
+/// Computes the buffers needed for shading from the outcome of the Newton-Raphson pass
+pub fn layers_from_samples(samples: &[Sample], scene: &Scene, timings: &mut Timings, verbose: bool) -> Layers {
+    let (width, height) = (scene.width, scene.height);
+    let table = samples.iter().map(|s| s.root).collect::<Vec<_>>();
+    let iterations = samples.iter().map(|s| s.iterations).collect::<Vec<_>>();
+    let values = samples.iter().map(|s| s.z).collect::<Vec<_>>();
+    // The size of a pixel of the default resolution, in pixels of this one
+    let resolution = width.max(height) as f64 / WIDTH.max(HEIGHT) as f64;
+
+    if verbose {
+        println!("Computing proximity matrix...");
+    }
+    let (distance, proximity) = timings.time("distance", || {
+        let pixel_size = scene.pixel_size();
+        let mut distance = GenericGrid::new(width, height);
+        for (i, sample) in samples.iter().enumerate() {
+            distance.set(i % width, i / width, sample.distance.map_or(f64::INFINITY, |d| d / pixel_size));
+        }
+        propagate_distances(&mut distance);
+
+        let mut proximity = GenericGrid::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                // The remap was tuned on the squared distances of the distance transform it replaces
+                let p = (distance.get(x, y).unwrap() / resolution).powi(2);
+                proximity.set(x, y, sigma(p.powf(0.55) / (p + 2.0).ln() / 16.0).powf(0.5));
+            }
+        }
+        (distance, proximity)
+    });
+
+    if verbose {
+        println!("Computing shadow...");
+    }
+    let (mut nabla_map_dx, mut nabla_map_dy) = timings.time("gradient", || {
+        let mut nabla_map_dx = GenericGrid::new(width, height);
+        let mut nabla_map_dy = GenericGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let nabla = normalize(discrete_nabla(&proximity, x, y).unwrap());
+                nabla_map_dx.set(x, y, nabla.0);
+                nabla_map_dy.set(x, y, nabla.1);
+            }
+        }
+        (nabla_map_dx, nabla_map_dy)
+    });
+
+    timings.time("blur", || {
+        gaussian_blur(&mut nabla_map_dx, BLUR_RADIUS * resolution);
+        gaussian_blur(&mut nabla_map_dy, BLUR_RADIUS * resolution);
+    });
+
+    Layers {
+        width,
+        height,
+        table,
+        iterations,
+        values,
+        distance,
+        proximity,
+        nabla_dx: nabla_map_dx,
+        nabla_dy: nabla_map_dy,
+    }
+}
+
+/// Lowers each distance to the smallest distance through its neighbors, with a two-pass chamfer distance transform:
+/// this fills the pixels without an estimate, which are infinite, and since a distance to a set cannot grow faster
+/// than the distance traveled, it also caps the estimate where it blows up, near the critical points of the orbits
+fn propagate_distances(distance: &mut GenericGrid<f64>) {
+    let (width, height) = (distance.width() as isize, distance.height() as isize);
+    let neighbors = [(-1, -1, SQRT_2), (0, -1, 1.0), (1, -1, SQRT_2), (-1, 0, 1.0)];
+
+    for sign in [1, -1] {
+        // The second pass goes backwards, looking at the neighbors below and on the right
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        let pixels: Box<dyn Iterator<Item = (isize, isize)>> = if sign > 0 { Box::new(pixels) } else { Box::new(pixels.rev()) };
+        for (x, y) in pixels {
+            let mut best = *distance.get(x as usize, y as usize).unwrap();
+            for &(dx, dy, weight) in neighbors.iter() {
+                let (nx, ny) = (x + sign * dx, y + sign * dy);
+                if nx >= 0 && ny >= 0 && nx < width && ny < height {
+                    best = best.min(distance.get(nx as usize, ny as usize).unwrap() + weight);
+                }
+            }
+            distance.set(x as usize, y as usize, best);
+        }
+    }
+}
+
+/// Returns the pixels whose root differs between `table` and `reference`
+pub fn mismatched_pixels(table: &[usize], reference: &[usize], width: usize) -> Vec<(usize, usize)> {
+    table.iter().zip(reference.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(i, _)| (i % width, i / width))
+        .collect()
+}
+
+pub fn sigma(x: f64) -> f64 {
+    1.0 - (-x).exp()
+}
+
+pub fn discrete_nabla(field: &GenericGrid<f64>, x: usize, y: usize) -> Option<(f64, f64)> {
+    let dx = if x == 0 {
+        field.get(x + 1, y)? - field.get(x, y)?
+    } else if x == field.width() - 1 {
+        field.get(x, y)? - field.get(x - 1, y)?
+    } else {
+        (field.get(x + 1, y)? - field.get(x - 1, y)?) / 2.0
+    };
+
+    let dy = if y == 0 {
+        field.get(x, y + 1)? - field.get(x, y)?
+    } else if y == field.height() - 1 {
+        field.get(x, y)? - field.get(x, y - 1)?
+    } else {
+        (field.get(x, y + 1)? - field.get(x, y - 1)?) / 2.0
+    };
+
+    Some((dx, dy))
+}
+
+#[inline]
+pub fn normalize((x, y): (f64, f64)) -> (f64, f64) {
+    if x == 0.0 && y == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let d = (x * x + y * y).sqrt();
+        (x / d, y / d)
+    }
+}
+
+#[inline]
+pub fn point_mul((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+    x1 * x2 + y1 * y2
+}
+
+#[test]
+fn test_resolution_independence() {
+    use num::complex::Complex;
+    // The same scene at two resolutions, where pixel (x, y) of the small one is pixel (3x, 3y) of the large one
+    let scene = Scene {
+        width: 40,
+        height: 30,
+        scale: 1.5,
+        relaxation: 1.0,
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-0.5, 0.866), Complex::new(-0.5, -0.866)],
+        ..Scene::default()
+    };
+    let large = Scene { width: 120, height: 90, ..scene.clone() };
+    let layers = |scene: &Scene| compute_layers(&PolyInfo::new(scene), scene, Method::Brute, &mut Timings::new(), false);
+    let (small, large) = (layers(&scene), layers(&large));
+
+    let mut difference = 0.0;
+    for y in 0..scene.height {
+        for x in 0..scene.width {
+            difference += (small.proximity.get(x, y).unwrap() - large.proximity.get(3 * x, 3 * y).unwrap()).abs();
+        }
+    }
+    assert!(difference / ((scene.width * scene.height) as f64) < 0.02);
+}
@@ -0,0 +1,396 @@
+//! Scenes: everything needed to render a single image, and their text file format.
+//!
+//! A scene file is a list of `key = value` lines; empty lines and lines starting with `#` are ignored,
+//! and keys that are not given keep their default value (see the constants in `main.rs`):
+//!
+//! ```text
+//! width = 1080
+//! height = 1350
+//! center = 0.5-0.25i
+//! scale = 36
+//! iterations = 1000
+//! epsilon = 0.02
+//! relaxation = 1.95
+//! shadow = 0.2, 0.9
+//! shadow_strength = 1
+//! background = 2
+//! form = roots
+//! multiplicity = modified
+//! roots = 1, -0.5+0.866i, -0.5-0.866i
+//! ```
+//!
+//! `relaxation` is the factor `A` in `z <- z - A * f(z) / f'(z)`, and `background` is the index of the root
+//! whose basin is drawn as the background (`none` for no background). `form` selects how the Newton step is
+//! computed, from the expanded `coefficients` (the default) or from the `roots`, see `NewtonForm`. `multiplicity` selects
+//! how repeated roots are handled: `plain`, `modified` (the default) or `reformulated`, see `MultiplicityMode`.
+//!
+//! `roots` is a list of complex numbers and root layouts, such as `polygon(5), scale(0.3, unity(7)), 0`, see
+//! `generators.rs`; scenes are written back with the roots expanded.
+//!
+//! The center can be given with more digits than an `f64` holds, for deep zooms; they are kept as written
+//! and used by the arbitrary-precision backend, see `deep.rs`.
+
+use std::fmt;
+use std::path::Path;
+use num::complex::Complex;
+use super::double_double::{ComplexDD, DoubleDouble};
+use super::generators::Generator;
+use super::newton::{MultiplicityMode, NewtonForm};
+use super::{WIDTH, HEIGHT, ITERATIONS, SCALE, EPSILON, A, SHADOW, SHADOW_STRENGTH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    pub width: usize,
+    pub height: usize,
+    /// Point of the complex plane at the center of the image
+    pub center: Complex<f64>,
+    /// The real and imaginary parts of the center as written in the scene file, if they have more digits
+    /// than `center` can hold
+    pub center_digits: Option<(String, String)>,
+    /// The center parsed from `center_digits`, in double-double precision
+    pub precise_center: Option<ComplexDD>,
+    /// Half of the extent of the complex plane covered by the largest side of the image
+    pub scale: f64,
+    pub iterations: usize,
+    pub epsilon: f64,
+    pub relaxation: f64,
+    pub shadow: (f64, f64),
+    pub shadow_strength: f64,
+    pub roots: Vec<Complex<f64>>,
+    /// Index of the root whose basin is drawn as the background
+    pub background: Option<usize>,
+    pub form: NewtonForm,
+    pub multiplicity_mode: MultiplicityMode,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            center: Complex::new(0.0, 0.0),
+            center_digits: None,
+            precise_center: None,
+            scale: SCALE,
+            iterations: ITERATIONS,
+            epsilon: EPSILON,
+            relaxation: A,
+            shadow: SHADOW,
+            shadow_strength: SHADOW_STRENGTH,
+            roots: vec![],
+            background: None,
+            form: NewtonForm::Coefficients,
+            multiplicity_mode: MultiplicityMode::Modified,
+        }
+    }
+}
+
+impl Scene {
+    /// Returns the point of the complex plane at pixel `(x, y)`
+    #[inline]
+    pub fn pixel_to_complex(&self, x: f64, y: f64) -> Complex<f64> {
+        self.pixel_offset(x, y) + self.center
+    }
+
+    /// Returns the offset from the center to the point at pixel `(x, y)`
+    #[inline]
+    pub fn pixel_offset(&self, x: f64, y: f64) -> Complex<f64> {
+        Complex::new(x - self.width as f64 / 2.0, y - self.height as f64 / 2.0)
+            / self.width.max(self.height) as f64 * 2.0 * self.scale
+    }
+
+    /// Moves the center to `center`, dropping the extra digits of the previous one
+    pub fn set_center(&mut self, center: Complex<f64>) {
+        self.center = center;
+        self.center_digits = None;
+        self.precise_center = None;
+    }
+
+    /// Returns the pixel coordinates of `z`; the inverse of `pixel_to_complex`
+    #[inline]
+    pub fn complex_to_pixel(&self, z: Complex<f64>) -> (f64, f64) {
+        let p = (z - self.center) / (2.0 * self.scale) * self.width.max(self.height) as f64;
+        (p.re + self.width as f64 / 2.0, p.im + self.height as f64 / 2.0)
+    }
+
+    /// The distance in the complex plane between two neighboring pixels
+    #[inline]
+    pub fn pixel_size(&self) -> f64 {
+        2.0 * self.scale / self.width.max(self.height) as f64
+    }
+
+    /// Returns a copy of the scene rendered at a different resolution, that still shows all of the original area
+    pub fn with_size(&self, width: usize, height: usize) -> Self {
+        let pixel_size = (self.pixel_size() * self.width as f64 / width as f64)
+            .max(self.pixel_size() * self.height as f64 / height as f64);
+
+        Self {
+            width,
+            height,
+            scale: pixel_size * width.max(height) as f64 / 2.0,
+            ..self.clone()
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut res = Self::default();
+
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: String| format!("line {}: {}", n + 1, msg);
+
+            let (key, value) = line.split_once('=').ok_or_else(|| error(String::from("expected `key = value`")))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "width" => res.width = parse_number(value).map_err(error)?,
+                "height" => res.height = parse_number(value).map_err(error)?,
+                "center" => {
+                    let (re, im) = split_complex(value).map_err(error)?;
+                    res.center = Complex::new(parse_finite(&re).map_err(error)?, parse_finite(&im).map_err(error)?);
+                    res.center_digits = (significant_digits(&re) > 17 || significant_digits(&im) > 17).then_some((re, im));
+                    res.precise_center = match &res.center_digits {
+                        Some((re, im)) => {
+                            Some(Complex::new(parse_precise(re).map_err(error)?, parse_precise(im).map_err(error)?))
+                        }
+                        None => None,
+                    };
+                }
+                "scale" => res.scale = parse_finite(value).map_err(error)?,
+                "iterations" => res.iterations = parse_number(value).map_err(error)?,
+                "epsilon" => res.epsilon = parse_finite(value).map_err(error)?,
+                "relaxation" => res.relaxation = parse_finite(value).map_err(error)?,
+                "shadow" => {
+                    let (x, y) = value.split_once(',').ok_or_else(|| error(String::from("expected `x, y`")))?;
+                    res.shadow = (parse_number(x.trim()).map_err(error)?, parse_number(y.trim()).map_err(error)?);
+                }
+                "shadow_strength" => res.shadow_strength = parse_number(value).map_err(error)?,
+                "background" => res.background = match value {
+                    "none" => None,
+                    _ => Some(parse_number(value).map_err(error)?),
+                },
+                "form" => {
+                    res.form = NewtonForm::from_name(value)
+                        .ok_or_else(|| error(format!("unknown form `{}`, expected coefficients or roots", value)))?;
+                }
+                "multiplicity" => {
+                    res.multiplicity_mode = MultiplicityMode::from_name(value).ok_or_else(|| error(format!(
+                        "unknown multiplicity mode `{}`, expected plain, modified or reformulated", value
+                    )))?;
+                }
+                "roots" => {
+                    res.roots = Generator::parse(value).map_err(error)?.roots();
+                }
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        if res.width == 0 || res.height == 0 {
+            return Err(String::from("width and height must be positive"));
+        }
+        if res.roots.is_empty() {
+            return Err(String::from("the scene needs at least one root"));
+        }
+        if let Some(background) = res.background {
+            if background >= res.roots.len() {
+                return Err(format!("background {} is not the index of a root", background));
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+impl fmt::Display for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "width = {}", self.width)?;
+        writeln!(f, "height = {}", self.height)?;
+        match &self.center_digits {
+            Some((re, im)) if im.starts_with('-') => writeln!(f, "center = {}{}i", re, im)?,
+            Some((re, im)) => writeln!(f, "center = {}+{}i", re, im)?,
+            None => writeln!(f, "center = {}", format_complex(self.center))?,
+        }
+        writeln!(f, "scale = {}", self.scale)?;
+        writeln!(f, "iterations = {}", self.iterations)?;
+        writeln!(f, "epsilon = {}", self.epsilon)?;
+        writeln!(f, "relaxation = {}", self.relaxation)?;
+        writeln!(f, "shadow = {}, {}", self.shadow.0, self.shadow.1)?;
+        writeln!(f, "shadow_strength = {}", self.shadow_strength)?;
+        match self.background {
+            Some(background) => writeln!(f, "background = {}", background)?,
+            None => writeln!(f, "background = none")?,
+        }
+        writeln!(f, "form = {}", self.form.name())?;
+        writeln!(f, "multiplicity = {}", self.multiplicity_mode.name())?;
+        writeln!(f, "roots = {}", self.roots.iter().map(|&z| format_complex(z)).collect::<Vec<_>>().join(", "))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid number `{}`", value))
+}
+
+/// Parses a number, rejecting infinities and NaN, as well as numbers too large for an `f64`
+fn parse_finite(value: &str) -> Result<f64, String> {
+    let x: f64 = parse_number(value)?;
+    if x.is_finite() {
+        Ok(x)
+    } else {
+        Err(format!("`{}` is not a finite number", value))
+    }
+}
+
+/// Parses the digits of the center in double-double precision, checking that the arbitrary-precision backend,
+/// which reads them again, accepts them too
+fn parse_precise(value: &str) -> Result<DoubleDouble, String> {
+    let error = || format!("invalid number `{}`", value);
+    #[cfg(feature = "deep")]
+    rug::Float::parse(value).map_err(|_| error())?;
+    DoubleDouble::parse(value).ok_or_else(error)
+}
+
+/// Parses complex numbers written as `a`, `bi`, `a+bi` or `a-bi`
+pub fn parse_complex(value: &str) -> Result<Complex<f64>, String> {
+    let error = || format!("invalid complex number `{}`", value);
+    let (re, im) = split_complex(value)?;
+
+    Ok(Complex::new(re.parse().map_err(|_| error())?, im.parse().map_err(|_| error())?))
+}
+
+/// Splits a complex number written like in `parse_complex` into the text of its real and imaginary parts,
+/// without checking that they are valid numbers
+pub fn split_complex(value: &str) -> Result<(String, String), String> {
+    let value = value.replace(' ', "");
+    if value.is_empty() {
+        return Err(String::from("empty complex number"));
+    }
+
+    let Some(imaginary) = value.strip_suffix('i') else {
+        return Ok((value, String::from("0")));
+    };
+
+    // Find the sign separating the real and imaginary parts, skipping those of exponents
+    let bytes = imaginary.as_bytes();
+    let split = (1..bytes.len()).rev().find(|&i| {
+        (bytes[i] == b'+' || bytes[i] == b'-') && bytes[i - 1] != b'e' && bytes[i - 1] != b'E'
+    });
+
+    let (re, im) = match split {
+        Some(i) => (&imaginary[..i], &imaginary[i..]),
+        None => ("0", imaginary),
+    };
+    let im = match im {
+        "" | "+" => "1",
+        "-" => "-1",
+        _ => im.strip_prefix('+').unwrap_or(im),
+    };
+
+    Ok((re.to_string(), im.to_string()))
+}
+
+/// Number of significant digits in the mantissa of a decimal number
+fn significant_digits(value: &str) -> usize {
+    let mantissa = value.split(['e', 'E']).next().unwrap_or_default();
+    mantissa.chars()
+        .filter(|c| c.is_ascii_digit())
+        .skip_while(|&c| c == '0')
+        .count()
+}
+
+pub fn format_complex(z: Complex<f64>) -> String {
+    if z.im.is_sign_negative() {
+        format!("{}-{}i", z.re, -z.im)
+    } else {
+        format!("{}+{}i", z.re, z.im)
+    }
+}
+
+#[test]
+fn test_parse_complex() {
+    assert_eq!(parse_complex("1.5"), Ok(Complex::new(1.5, 0.0)));
+    assert_eq!(parse_complex("-2i"), Ok(Complex::new(0.0, -2.0)));
+    assert_eq!(parse_complex("i"), Ok(Complex::new(0.0, 1.0)));
+    assert_eq!(parse_complex("-0.5 + 0.25i"), Ok(Complex::new(-0.5, 0.25)));
+    assert_eq!(parse_complex("1e-3-2E+2i"), Ok(Complex::new(1e-3, -200.0)));
+    assert_eq!(parse_complex("3-i"), Ok(Complex::new(3.0, -1.0)));
+    assert!(parse_complex("1+2j").is_err());
+    assert!(parse_complex("").is_err());
+
+    let z = Complex::new(0.1, -1.0 / 3.0);
+    assert_eq!(parse_complex(&format_complex(z)), Ok(z));
+}
+
+#[test]
+fn test_scene_roundtrip() {
+    let scene = Scene {
+        width: 300,
+        height: 200,
+        center: Complex::new(0.25, -1.5),
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-0.5, 0.75), Complex::new(0.0, 0.0)],
+        background: Some(2),
+        form: NewtonForm::Roots,
+        multiplicity_mode: MultiplicityMode::Reformulated,
+        ..Scene::default()
+    };
+    assert_eq!(Scene::parse(&scene.to_string()), Ok(scene.clone()));
+
+    let partial = Scene::parse("# comment\nroots = 1, -1\n\nscale = 2").unwrap();
+    assert_eq!(partial.roots, vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)]);
+    assert_eq!(partial.scale, 2.0);
+    assert_eq!(partial.width, WIDTH);
+
+    let deep = Scene::parse("roots = 1\ncenter = -0.1234567890123456789012345 + 0.5i").unwrap();
+    assert_eq!(deep.center_digits, Some((String::from("-0.1234567890123456789012345"), String::from("0.5"))));
+    assert_eq!(deep.center, Complex::new(-0.12345678901234568, 0.5));
+    assert_eq!(Scene::parse(&deep.to_string()), Ok(deep));
+    assert_eq!(partial.center_digits, None);
+    assert!(Scene::parse("roots = 1\ncenter = 0.12345678901234567890123e-2000000000").is_err());
+    assert!(Scene::parse("roots = 1\ncenter = 1e400").is_err());
+    assert!(Scene::parse("roots = 1\nscale = inf").is_err());
+    assert!(Scene::parse("roots = 1\nrelaxation = NaN").is_err());
+    assert!(Scene::parse("roots = 1\ncenter = 0.12345678901234567890123e99999999999").is_err());
+
+    let generated = Scene::parse("roots = polygon(3, 2), 0.5i").unwrap();
+    assert_eq!(generated.roots.len(), 4);
+    assert_eq!(Scene::parse(&generated.to_string()), Ok(generated));
+
+    assert!(Scene::parse("roots = 1\nfoo = 2").is_err());
+    assert!(Scene::parse("width = 10").is_err());
+    assert!(Scene::parse("roots = 1\nbackground = 1").is_err());
+    assert!(Scene::parse("roots = 1\nform = expanded").is_err());
+    assert!(Scene::parse("roots = 1\nmultiplicity = double").is_err());
+    assert!(Scene::parse("roots = polygon(3, 2, 1, 0)").is_err());
+}
+
+#[test]
+fn test_pixel_mapping() {
+    let scene = Scene {
+        width: 300,
+        height: 200,
+        center: Complex::new(1.0, 2.0),
+        scale: 3.0,
+        ..Scene::default()
+    };
+    assert_eq!(scene.pixel_to_complex(150.0, 100.0), Complex::new(1.0, 2.0));
+    assert_eq!(scene.pixel_to_complex(300.0, 100.0), Complex::new(4.0, 2.0));
+    let (x, y) = scene.complex_to_pixel(scene.pixel_to_complex(12.0, 34.0));
+    assert!((x - 12.0).abs() < 1e-9 && (y - 34.0).abs() < 1e-9);
+    assert_eq!(scene.pixel_size(), 0.02);
+
+    let small = scene.with_size(30, 30);
+    assert!((small.pixel_size() - 0.2).abs() < 1e-9);
+    assert_eq!(small.center, scene.center);
+}
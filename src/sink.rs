@@ -0,0 +1,292 @@
+//! Destinations for the rendered frames of an animation.
+//!
+//! A frame sink either writes each frame to its own numbered image, or encodes the whole animation
+//! into a single stream: an animated GIF or APNG, or a YUV4MPEG2 (`.y4m`) stream that can be written to a file
+//! or piped into an external encoder, like `ffmpeg -i - output.mp4`.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use image::{Rgb32FImage, DynamicImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+use super::output::{self, OutputFormat};
+
+/// Where the frames of an animation go, as selected on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    /// One image per frame; `{}` in the path is replaced with the frame number
+    Images { template: String, format: OutputFormat },
+    Gif(String),
+    Apng(String),
+    Y4m(String),
+    /// A y4m stream written to the standard input of a shell command
+    Pipe(String),
+}
+
+/// Timing settings of an animation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Playback {
+    pub fps: u32,
+    /// Number of times the animation is played, 0 for infinitely
+    pub loops: u32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            loops: 0,
+        }
+    }
+}
+
+pub trait FrameSink {
+    /// Writes frame number `frame`; frames are given in increasing order
+    fn write_frame(&mut self, frame: usize, image: &Rgb32FImage) -> io::Result<()>;
+
+    /// Returns true if a valid copy of frame `frame` was already written by a previous run
+    fn has_frame(&self, _frame: usize) -> bool {
+        false
+    }
+
+    /// Flushes the stream, needs to be called once all frames are written
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// Opens the sink for `target`; `frames` is the total number of frames that will be written
+pub fn open(
+    target: &Target,
+    playback: Playback,
+    width: usize,
+    height: usize,
+    frames: usize
+) -> io::Result<Box<dyn FrameSink>> {
+    Ok(match target {
+        Target::Images { template, format } => Box::new(ImageSequence {
+            template: template.clone(),
+            format: *format,
+            width,
+            height,
+        }),
+        Target::Gif(path) => {
+            let mut encoder = GifEncoder::new(BufWriter::new(create(path)?));
+            encoder.set_repeat(match playback.loops {
+                0 => Repeat::Infinite,
+                n => Repeat::Finite((n - 1).min(u16::MAX as u32) as u16),
+            }).map_err(io::Error::other)?;
+            Box::new(GifSink {
+                encoder,
+                delay: Delay::from_numer_denom_ms(1000, playback.fps),
+            })
+        }
+        Target::Apng(path) => {
+            let mut encoder = png::Encoder::new(BufWriter::new(create(path)?), width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(frames as u32, playback.loops).map_err(io::Error::other)?;
+            let fps = u16::try_from(playback.fps).map_err(|_| io::Error::other("frame rate too high for APNG"))?;
+            encoder.set_frame_delay(1, fps).map_err(io::Error::other)?;
+            Box::new(ApngSink {
+                writer: encoder.write_header().map_err(io::Error::other)?,
+            })
+        }
+        Target::Y4m(path) => Box::new(Y4mSink::new(BufWriter::new(create(path)?), playback, width, height)?),
+        Target::Pipe(command) => Box::new(PipeSink::spawn(command, playback, width, height)?),
+    })
+}
+
+/// Creates the file at `path`, along with its parent directories
+fn create(path: impl AsRef<Path>) -> io::Result<File> {
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path)
+}
+
+pub struct ImageSequence {
+    template: String,
+    format: OutputFormat,
+    width: usize,
+    height: usize,
+}
+
+impl ImageSequence {
+    pub fn frame_path(&self, frame: usize) -> PathBuf {
+        PathBuf::from(self.template.replace("{}", &frame.to_string()))
+    }
+}
+
+impl FrameSink for ImageSequence {
+    /// Frames are first written to a temporary file, which is then renamed,
+    /// so that an interrupted run never leaves a truncated image behind
+    fn write_frame(&mut self, frame: usize, image: &Rgb32FImage) -> io::Result<()> {
+        let path = self.frame_path(frame);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+
+        output::save(image, &tmp_path, self.format).map_err(io::Error::other)?;
+        fs::rename(tmp_path, path)
+    }
+
+    fn has_frame(&self, frame: usize) -> bool {
+        match image::open(self.frame_path(frame)) {
+            Ok(image) => image.width() as usize == self.width && image.height() as usize == self.height,
+            Err(_) => false,
+        }
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct GifSink {
+    encoder: GifEncoder<BufWriter<File>>,
+    delay: Delay,
+}
+
+impl FrameSink for GifSink {
+    fn write_frame(&mut self, _frame: usize, image: &Rgb32FImage) -> io::Result<()> {
+        let rgba = DynamicImage::ImageRgb8(output::quantize8(image)).into_rgba8();
+        self.encoder.encode_frame(Frame::from_parts(rgba, 0, 0, self.delay)).map_err(io::Error::other)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        // The trailer is written when the encoder is dropped
+        Ok(())
+    }
+}
+
+pub struct ApngSink {
+    writer: png::Writer<BufWriter<File>>,
+}
+
+impl FrameSink for ApngSink {
+    fn write_frame(&mut self, _frame: usize, image: &Rgb32FImage) -> io::Result<()> {
+        self.writer.write_image_data(output::quantize8(image).as_raw()).map_err(io::Error::other)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.writer.finish().map_err(io::Error::other)
+    }
+}
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream, in 8-bit 4:4:4 with BT.709 limited-range coefficients
+pub struct Y4mSink<W: Write> {
+    out: W,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mSink<W> {
+    pub fn new(mut out: W, playback: Playback, width: usize, height: usize) -> io::Result<Self> {
+        writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=LIMITED", width, height, playback.fps)?;
+        Ok(Self {
+            out,
+            planes: vec![0; width * height * 3],
+        })
+    }
+}
+
+impl<W: Write> FrameSink for Y4mSink<W> {
+    fn write_frame(&mut self, _frame: usize, image: &Rgb32FImage) -> io::Result<()> {
+        let size = (image.width() * image.height()) as usize;
+        let (y_plane, rest) = self.planes.split_at_mut(size);
+        let (u_plane, v_plane) = rest.split_at_mut(size);
+
+        for (i, pixel) in image.pixels().enumerate() {
+            let [y, u, v] = rgb_to_ycbcr(pixel.0);
+            y_plane[i] = y;
+            u_plane[i] = u;
+            v_plane[i] = v;
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&self.planes)
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Streams the frames as y4m into the standard input of a shell command
+pub struct PipeSink {
+    child: Child,
+    stream: Y4mSink<BufWriter<ChildStdin>>,
+}
+
+impl PipeSink {
+    pub fn spawn(command: &str, playback: Playback, width: usize, height: usize) -> io::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin should be piped");
+
+        Ok(Self {
+            child,
+            stream: Y4mSink::new(BufWriter::new(stdin), playback, width, height)?,
+        })
+    }
+}
+
+impl FrameSink for PipeSink {
+    fn write_frame(&mut self, frame: usize, image: &Rgb32FImage) -> io::Result<()> {
+        self.stream.write_frame(frame, image)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let PipeSink { mut child, stream } = *self;
+        // Dropping the stream closes the pipe, letting the encoder know that there are no more frames
+        Box::new(stream).finish()?;
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("encoder exited with {}", status)))
+        }
+    }
+}
+
+/// Converts an sRGB color to 8-bit, limited-range BT.709 Y'CbCr
+fn rgb_to_ycbcr([r, g, b]: [f32; 3]) -> [u8; 3] {
+    let (r, g, b) = (r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let cb = (b - y) / 1.8556;
+    let cr = (r - y) / 1.5748;
+
+    [
+        (16.0 + 219.0 * y).round() as u8,
+        (128.0 + 224.0 * cb).round() as u8,
+        (128.0 + 224.0 * cr).round() as u8,
+    ]
+}
+
+#[test]
+fn test_y4m_sink() {
+    let image = Rgb32FImage::from_fn(4, 2, |x, _y| if x < 2 { image::Rgb([0.0, 0.0, 0.0]) } else { image::Rgb([1.0, 1.0, 1.0]) });
+    let mut buffer = Vec::new();
+
+    let mut sink = Box::new(Y4mSink::new(&mut buffer, Playback::default(), 4, 2).unwrap());
+    sink.write_frame(0, &image).unwrap();
+    sink.write_frame(1, &image).unwrap();
+    sink.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W4 H2 F30:1 Ip A1:1 C444 XCOLORRANGE=LIMITED\n";
+    assert_eq!(&buffer[..header.len()], header);
+    assert_eq!(buffer.len(), header.len() + 2 * (6 + 3 * 8));
+
+    let frame = &buffer[header.len()..header.len() + 6 + 3 * 8];
+    assert_eq!(&frame[..6], b"FRAME\n");
+    assert_eq!(&frame[6..14], &[16, 16, 235, 235, 16, 16, 235, 235]);
+    assert!(frame[14..].iter().all(|&c| c == 128));
+}
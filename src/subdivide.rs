@@ -0,0 +1,185 @@
+//! Mariani-Silver subdivision: the Newton-Raphson method is only run on the border of a rectangle, and if the whole
+//! border converged to the same root, the interior is filled with that root, its distance to the boundary being
+//! propagated from the border (see `render.rs`); otherwise the rectangle is split in two
+//! halves, which are handled the same way.
+//!
+//! The basins are not always simply connected, so unlike the brute-force pass, this can miss the small islands
+//! of another basin that lie fully inside a rectangle; `--verify` reports how many pixels differ.
+
+use std::sync::Mutex;
+use scoped_threadpool::Pool;
+use super::{PolyInfo, THREADS};
+use super::newton::{calc_pixel, Sample};
+use super::progress::ProgressBar;
+use super::scene::Scene;
+
+/// Size of the tiles the image is first split into, which are processed in parallel
+const TILE: usize = 64;
+/// Rectangles narrower than this are computed entirely
+const MIN_SIZE: usize = 4;
+
+/// A tile of the image, with the samples that were computed so far
+struct Tile<'a> {
+    info: &'a PolyInfo,
+    scene: &'a Scene,
+    x: usize,
+    y: usize,
+    width: usize,
+    samples: Vec<Option<Sample>>,
+    computed: usize,
+}
+
+impl Tile<'_> {
+    fn get(&mut self, x: usize, y: usize) -> Sample {
+        let index = (x - self.x) + (y - self.y) * self.width;
+        match self.samples[index] {
+            Some(sample) => sample,
+            None => {
+                let sample = calc_pixel(x, y, self.info, self.scene);
+                self.samples[index] = Some(sample);
+                self.computed += 1;
+                sample
+            }
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, sample: Sample) {
+        self.samples[(x - self.x) + (y - self.y) * self.width] = Some(sample);
+    }
+
+    /// Handles the rectangle between `(x0, y0)` and `(x1, y1)`, inclusive
+    fn subdivide(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x1 - x0 < MIN_SIZE || y1 - y0 < MIN_SIZE {
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.get(x, y);
+                }
+            }
+            return;
+        }
+
+        let border = (x0..=x1).flat_map(|x| [(x, y0), (x, y1)])
+            .chain((y0 + 1..y1).flat_map(|y| [(x0, y), (x1, y)]))
+            .map(|(x, y)| self.get(x, y))
+            .collect::<Vec<_>>();
+
+        let root = border[0].root;
+        if root < self.info.roots.len() && border.iter().all(|sample| sample.root == root) {
+            let fill = Sample {
+                root,
+                iterations: border.iter().map(|sample| sample.iterations).min().unwrap(),
+                z: self.info.roots[root],
+                distance: None,
+            };
+            for y in y0 + 1..y1 {
+                for x in x0 + 1..x1 {
+                    self.set(x, y, fill);
+                }
+            }
+            return;
+        }
+
+        // Split along the longest side, both halves sharing the middle line
+        if x1 - x0 >= y1 - y0 {
+            let mid = (x0 + x1) / 2;
+            self.subdivide(x0, y0, mid, y1);
+            self.subdivide(mid, y0, x1, y1);
+        } else {
+            let mid = (y0 + y1) / 2;
+            self.subdivide(x0, y0, x1, mid);
+            self.subdivide(x0, mid, x1, y1);
+        }
+    }
+}
+
+/// Runs the Newton-Raphson method on the image by subdividing it, returning the samples
+/// and the number of pixels that were actually computed
+pub fn compute_samples(info: &PolyInfo, scene: &Scene, verbose: bool) -> (Vec<Sample>, usize) {
+    let (width, height) = (scene.width, scene.height);
+    let tiles = (0..height).step_by(TILE)
+        .flat_map(|y| (0..width).step_by(TILE).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+
+    // Tiles overlap by one pixel, so that each of them has its whole border: the borders are computed first,
+    // so that neighbouring tiles do not both compute the pixels they share
+    let on_border = |x: usize, y: usize| {
+        x.is_multiple_of(TILE) || y.is_multiple_of(TILE) || x == width - 1 || y == height - 1
+    };
+    let mut borders = vec![None; width * height];
+    let mut pool = Pool::new(THREADS);
+    pool.scoped(|scoped| {
+        for (y, row) in borders.chunks_mut(width).enumerate() {
+            scoped.execute(move || {
+                for (x, sample) in row.iter_mut().enumerate().filter(|&(x, _)| on_border(x, y)) {
+                    *sample = Some(calc_pixel(x, y, info, scene));
+                }
+            });
+        }
+    });
+    let borders = &borders;
+
+    let done = Mutex::new(Vec::new());
+    let progress = verbose.then(|| ProgressBar::new("Newton", tiles.len()));
+    pool.scoped(|scoped| {
+        for &(x, y) in tiles.iter() {
+            let (done, progress) = (&done, &progress);
+            scoped.execute(move || {
+                let (x1, y1) = ((x + TILE).min(width - 1), (y + TILE).min(height - 1));
+                let mut tile = Tile {
+                    info,
+                    scene,
+                    x,
+                    y,
+                    width: x1 - x + 1,
+                    samples: (y..=y1).flat_map(|y| (x..=x1).map(move |x| borders[x + y * width])).collect(),
+                    computed: 0,
+                };
+                tile.subdivide(x, y, x1, y1);
+
+                done.lock().unwrap().push(tile);
+                if let Some(progress) = progress {
+                    progress.inc();
+                }
+            });
+        }
+    });
+
+    let mut samples = vec![Sample::default(); width * height];
+    let mut computed = borders.iter().filter(|sample| sample.is_some()).count();
+    for tile in done.into_inner().unwrap() {
+        for (i, sample) in tile.samples.iter().enumerate() {
+            samples[(tile.x + i % tile.width) + (tile.y + i / tile.width) * width] = sample.unwrap();
+        }
+        computed += tile.computed;
+    }
+    (samples, computed)
+}
+
+#[test]
+fn test_subdivide() {
+    use num::complex::Complex;
+    use super::render::compute_samples as brute_force;
+
+    let scene = Scene {
+        width: 150,
+        height: 90,
+        scale: 2.0,
+        iterations: 200,
+        relaxation: 1.0,
+        roots: vec![Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0), Complex::new(0.0, 1.5)],
+        ..Scene::default()
+    };
+    let info = PolyInfo::new(&scene);
+
+    let (samples, computed) = compute_samples(&info, &scene, false);
+    assert!(computed < scene.width * scene.height);
+
+    // With a single root, only the borders of the tiles are computed, each pixel once: the rows 0, 64 and 89
+    // and the columns 0, 64, 128 and 149
+    let single = Scene { roots: vec![Complex::new(0.5, 0.5)], ..scene.clone() };
+    assert_eq!(compute_samples(&PolyInfo::new(&single), &single, false).1, 3 * 150 + 4 * 90 - 3 * 4);
+
+    let brute = brute_force(&info, &scene, false);
+    let mismatches = brute.iter().zip(samples.iter()).filter(|(a, b)| a.root != b.root).count();
+    assert!(mismatches * 100 < brute.len(), "{} mismatched pixels", mismatches);
+}
@@ -0,0 +1,202 @@
+//! Parameter sweeps: a grid of thumbnails of the same scene, with one parameter changing along the columns and
+//! optionally another one along the rows, composed into a single labelled image.
+//!
+//! An axis is written `parameter=start..end/count`, for `count` evenly spaced values from `start` to `end`,
+//! or `parameter=a,b,c` for a list of values, like `relaxation=1..2/5` or `epsilon=0.005,0.02,0.08`.
+
+use image::{GenericImage, Rgb32FImage};
+use super::overlay::{draw_text, text_size};
+use super::scene::Scene;
+
+/// A scene parameter that can be swept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parameter {
+    Relaxation,
+    Epsilon,
+    ShadowStrength,
+    Iterations,
+    Scale,
+}
+
+impl Parameter {
+    pub const NAMES: &'static str = "relaxation, epsilon, shadow_strength, iterations, scale";
+
+    /// Takes the keys of the scene files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "relaxation" => Some(Self::Relaxation),
+            "epsilon" => Some(Self::Epsilon),
+            "shadow_strength" => Some(Self::ShadowStrength),
+            "iterations" => Some(Self::Iterations),
+            "scale" => Some(Self::Scale),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Relaxation => "relaxation",
+            Self::Epsilon => "epsilon",
+            Self::ShadowStrength => "shadow_strength",
+            Self::Iterations => "iterations",
+            Self::Scale => "scale",
+        }
+    }
+
+    /// Sets the parameter of `scene` to `value`, rounded for integer parameters
+    pub fn apply(self, scene: &mut Scene, value: f64) {
+        match self {
+            Self::Relaxation => scene.relaxation = value,
+            Self::Epsilon => scene.epsilon = value,
+            Self::ShadowStrength => scene.shadow_strength = value,
+            Self::Iterations => scene.iterations = value.round() as usize,
+            Self::Scale => scene.scale = value,
+        }
+    }
+
+    fn check(self, value: f64) -> Result<(), String> {
+        let valid = match self {
+            Self::Relaxation | Self::ShadowStrength => value.is_finite(),
+            Self::Epsilon | Self::Scale => value > 0.0 && value.is_finite(),
+            Self::Iterations => value >= 1.0 && value.is_finite(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("invalid value {} for {}", value, self.name()))
+        }
+    }
+}
+
+/// The values a parameter takes along the columns or the rows of the grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct Axis {
+    pub parameter: Parameter,
+    pub values: Vec<f64>,
+}
+
+impl Axis {
+    /// Parses `parameter=start..end/count` or `parameter=a,b,c`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, values) = spec.split_once('=').ok_or_else(|| format!("expected `parameter=values`, got `{}`", spec))?;
+        let parameter = Parameter::from_name(name.trim()).ok_or_else(|| {
+            format!("unknown parameter `{}`, expected one of {}", name.trim(), Parameter::NAMES)
+        })?;
+        let number = |value: &str| value.trim().parse::<f64>().map_err(|_| format!("invalid number `{}` in `{}`", value, spec));
+
+        let values = match values.split_once("..") {
+            Some((start, end)) => {
+                let (end, count) = end.split_once('/').ok_or_else(|| format!("missing `/count` in `{}`", spec))?;
+                let (start, end) = (number(start)?, number(end)?);
+                let count = count.trim().parse::<usize>().ok().filter(|&count| count >= 1).ok_or_else(|| {
+                    format!("invalid number of values `{}` in `{}`", count, spec)
+                })?;
+                if count == 1 {
+                    vec![start]
+                } else {
+                    (0..count).map(|i| start + (end - start) * i as f64 / (count - 1) as f64).collect()
+                }
+            }
+            None => values.split(',').map(number).collect::<Result<Vec<_>, _>>()?,
+        };
+        for &value in values.iter() {
+            parameter.check(value)?;
+        }
+        Ok(Self { parameter, values })
+    }
+
+    fn label(&self, i: usize) -> String {
+        format!("{}={}", self.parameter.name().to_ascii_uppercase(), format_value(self.values[i]))
+    }
+}
+
+/// Formats `value` with at most 4 decimals, without trailing zeros
+fn format_value(value: f64) -> String {
+    let res = format!("{:.4}", value);
+    let res = res.trim_end_matches('0').trim_end_matches('.');
+    if res == "-0" { String::from("0") } else { res.to_string() }
+}
+
+/// The scenes of the grid, row by row, each `1 / max(columns, rows)` the size of `scene`
+pub fn cell_scenes(scene: &Scene, columns: &Axis, rows: Option<&Axis>) -> Vec<Scene> {
+    let row_count = rows.map_or(1, |rows| rows.values.len());
+    let divisor = columns.values.len().max(row_count);
+    let tile = Scene {
+        width: (scene.width / divisor).max(1),
+        height: (scene.height / divisor).max(1),
+        ..scene.clone()
+    };
+
+    (0..row_count).flat_map(|row| columns.values.iter().map(move |&value| (row, value)))
+        .map(|(row, value)| {
+            let mut cell = tile.clone();
+            columns.parameter.apply(&mut cell, value);
+            if let Some(rows) = rows {
+                rows.parameter.apply(&mut cell, rows.values[row]);
+            }
+            cell
+        })
+        .collect()
+}
+
+/// Composes the thumbnails of `cell_scenes` into one image, with the values of the columns above them and those of
+/// the rows on their left
+pub fn compose(tiles: &[Rgb32FImage], columns: &Axis, rows: Option<&Axis>, scale: usize) -> Rgb32FImage {
+    let (width, height) = (tiles[0].width(), tiles[0].height());
+    let margin = 2 * scale as u32;
+    let text_height = text_size("", scale).1 as u32;
+    let left = match rows {
+        Some(rows) => (0..rows.values.len()).map(|i| text_size(&rows.label(i), scale).0 as u32).max().unwrap() + margin,
+        None => 0,
+    };
+    let top = text_height + margin;
+    let row_count = tiles.len() as u32 / columns.values.len() as u32;
+
+    let mut image = Rgb32FImage::new(
+        left + columns.values.len() as u32 * (width + margin),
+        top + row_count * (height + margin)
+    );
+    for (i, tile) in tiles.iter().enumerate() {
+        let (column, row) = (i as u32 % columns.values.len() as u32, i as u32 / columns.values.len() as u32);
+        image.copy_from(tile, left + column * (width + margin), top + row * (height + margin)).unwrap();
+    }
+    for column in 0..columns.values.len() {
+        let x = left + column as u32 * (width + margin);
+        draw_text(&mut image, (x as isize, 0), &columns.label(column), scale);
+    }
+    if let Some(rows) = rows {
+        for row in 0..rows.values.len() {
+            let y = top + row as u32 * (height + margin) + height.saturating_sub(text_height) / 2;
+            draw_text(&mut image, (0, y as isize), &rows.label(row), scale);
+        }
+    }
+    image
+}
+
+#[test]
+fn test_sweep() {
+    let axis = Axis::parse("relaxation=1..2/5").unwrap();
+    assert_eq!(axis, Axis { parameter: Parameter::Relaxation, values: vec![1.0, 1.25, 1.5, 1.75, 2.0] });
+    assert_eq!(axis.label(1), "RELAXATION=1.25");
+    assert_eq!(Axis::parse(" epsilon = 0.01, 0.1").unwrap().values, vec![0.01, 0.1]);
+    assert_eq!(Axis::parse("scale=3..5/1").unwrap().values, vec![3.0]);
+    assert!(Axis::parse("a=1..2/3").is_err());
+    assert!(Axis::parse("relaxation=1..2").is_err());
+    assert!(Axis::parse("relaxation=1..2/0").is_err());
+    assert!(Axis::parse("epsilon=0,1").is_err());
+    assert_eq!(format_value(0.1 + 0.2), "0.3");
+    assert_eq!(format_value(-1e-9), "0");
+    assert_eq!(format_value(4.0 / 3.0), "1.3333");
+
+    let scene = Scene { width: 300, height: 200, roots: vec![num::complex::Complex::new(1.0, 0.0)], ..Scene::default() };
+    let rows = Axis::parse("iterations=10,20").unwrap();
+    let cells = cell_scenes(&scene, &axis, Some(&rows));
+    assert_eq!(cells.len(), 10);
+    assert_eq!((cells[0].width, cells[0].height), (60, 40));
+    assert_eq!((cells[6].relaxation, cells[6].iterations), (1.25, 20));
+
+    let tiles = vec![Rgb32FImage::new(60, 40); 10];
+    let image = compose(&tiles, &axis, Some(&rows), 1);
+    let left = text_size("ITERATIONS=20", 1).0 as u32 + 2;
+    assert_eq!(image.dimensions(), (left + 5 * 62, 9 + 2 * 42));
+}
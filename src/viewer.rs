@@ -0,0 +1,250 @@
+//! Interactive viewer, rendered in software.
+//!
+//! The view is rendered progressively in a background thread, see `progressive.rs`: first every 16th pixel,
+//! then every 8th, 4th, 2nd and finally every pixel; any change to the view restarts the rendering.
+//!
+//! - drag with the left mouse button to pan, or to move a root when the cursor is on its marker
+//! - scroll, or press `+` and `-`, to zoom in and out
+//! - press `S` to export the current view as a scene file, rendered at the resolution of the original scene
+//! - press `R` to reset the view, and `Escape` to quit
+
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use num::complex::Complex;
+use super::PolyInfo;
+use super::draw::shade;
+use super::output::quantize8;
+use super::progress::Timings;
+use super::progressive::Progressive;
+use super::render::layers_from_samples;
+use super::scene::{Scene, format_complex};
+
+/// Size of the largest side of the window
+const MAX_SIZE: usize = 900;
+/// Radius of the root markers, in pixels
+const ROOT_RADIUS: f64 = 6.0;
+const ZOOM_STEP: f64 = 1.25;
+
+/// A render request, tagged with a generation number to discard outdated results
+struct Job {
+    generation: usize,
+    scene: Scene,
+}
+
+struct Preview {
+    generation: usize,
+    /// Step of the pass this preview comes from
+    step: usize,
+    buffer: Vec<u32>,
+}
+
+pub fn run(scene: Scene) {
+    let (width, height) = if scene.width >= scene.height {
+        (MAX_SIZE, (MAX_SIZE * scene.height / scene.width).max(1))
+    } else {
+        ((MAX_SIZE * scene.width / scene.height).max(1), MAX_SIZE)
+    };
+
+    let mut window = Window::new("Newton fractal", width, height, WindowOptions::default())
+        .unwrap_or_else(|e| panic!("cannot open the viewer window: {}", e));
+    window.set_target_fps(60);
+
+    let (job_tx, job_rx) = channel::<Job>();
+    let (preview_tx, preview_rx) = channel::<Preview>();
+    thread::spawn(move || render_worker(job_rx, preview_tx));
+
+    let initial = scene.with_size(width, height);
+    let mut view = initial.clone();
+    let mut generation = 0;
+    let mut buffer = vec![0u32; width * height];
+    let mut step = 0;
+    job_tx.send(Job { generation, scene: view.clone() }).unwrap();
+
+    let mut last_mouse: Option<(f32, f32)> = None;
+    let mut dragged_root: Option<usize> = None;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let mut changed = false;
+        let mouse = window.get_mouse_pos(MouseMode::Clamp);
+
+        // Panning and dragging roots
+        if window.get_mouse_down(MouseButton::Left) {
+            if let (Some((x, y)), Some((last_x, last_y))) = (mouse, last_mouse) {
+                match dragged_root {
+                    Some(index) => {
+                        view.roots[index] = view.pixel_to_complex(x as f64, y as f64);
+                    }
+                    None => {
+                        let offset = Complex::new((x - last_x) as f64, (y - last_y) as f64) * view.pixel_size();
+                        view.set_center(view.center - offset);
+                    }
+                }
+                changed = x != last_x || y != last_y;
+            } else if let Some((x, y)) = mouse {
+                dragged_root = find_root(&view, x as f64, y as f64);
+            }
+            last_mouse = mouse;
+        } else {
+            last_mouse = None;
+            dragged_root = None;
+        }
+
+        // Zooming
+        let mut zoom = 0.0;
+        if let Some((_, scroll)) = window.get_scroll_wheel() {
+            zoom += scroll.signum() as f64;
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes) {
+            zoom += 1.0;
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes) {
+            zoom -= 1.0;
+        }
+        if zoom != 0.0 {
+            let factor = ZOOM_STEP.powf(-zoom);
+            // Keep the point under the cursor in place
+            let anchor = match mouse {
+                Some((x, y)) => view.pixel_to_complex(x as f64, y as f64),
+                None => view.center,
+            };
+            view.set_center(anchor + (view.center - anchor) * factor);
+            view.scale *= factor;
+            changed = true;
+        }
+
+        if window.is_key_pressed(Key::R, KeyRepeat::No) {
+            view = initial.clone();
+            changed = true;
+        }
+
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            let exported = view.with_size(scene.width, scene.height);
+            match export_scene(&exported) {
+                Ok(path) => println!("Exported the view to {}", path.display()),
+                Err(e) => eprintln!("Error: cannot export the view: {}", e),
+            }
+        }
+
+        if changed {
+            generation += 1;
+            step = 0;
+            job_tx.send(Job { generation, scene: view.clone() }).unwrap();
+        }
+
+        loop {
+            match preview_rx.try_recv() {
+                Ok(preview) if preview.generation == generation => {
+                    buffer = preview.buffer;
+                    step = preview.step;
+                }
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => panic!("the render thread stopped"),
+            }
+        }
+
+        let mut display = buffer.clone();
+        draw_root_markers(&mut display, &view);
+        window.update_with_buffer(&display, width, height).unwrap();
+
+        window.set_title(&format!(
+            "Newton fractal - center {} - scale {:.3e}{}",
+            format_complex(view.center),
+            view.scale,
+            match step {
+                0 => String::from(" - rendering"),
+                1 => String::new(),
+                _ => format!(" - rendering (1/{})", step),
+            }
+        ));
+    }
+}
+
+/// Renders the requested views at increasing resolutions, restarting whenever a newer request arrives
+fn render_worker(jobs: Receiver<Job>, previews: Sender<Preview>) {
+    let mut pending: Option<Job> = None;
+
+    loop {
+        let job = match pending.take() {
+            Some(job) => job,
+            None => match jobs.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            },
+        };
+
+        let poly_info = PolyInfo::new(&job.scene);
+        let mut renderer = Progressive::new(&job.scene);
+        while renderer.refine(&poly_info) {
+            // Only keep the most recent request
+            while let Ok(newer) = jobs.try_recv() {
+                pending = Some(newer);
+            }
+            if pending.is_some() {
+                break;
+            }
+
+            let layers = layers_from_samples(&renderer.samples(), &job.scene, &mut Timings::new(), false);
+            let image = quantize8(&shade(&layers, &job.scene));
+            let buffer = image.pixels()
+                .map(|pixel| {
+                    let [r, g, b] = pixel.0;
+                    (r as u32) << 16 | (g as u32) << 8 | b as u32
+                })
+                .collect();
+
+            let step = renderer.step().unwrap();
+            if previews.send(Preview { generation: job.generation, step, buffer }).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the index of the root whose marker is under `(x, y)`
+fn find_root(view: &Scene, x: f64, y: f64) -> Option<usize> {
+    view.roots.iter()
+        .map(|&root| {
+            let (rx, ry) = view.complex_to_pixel(root);
+            (rx - x).hypot(ry - y)
+        })
+        .enumerate()
+        .filter(|(_, distance)| *distance <= ROOT_RADIUS + 2.0)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Draws a ring around each root, in white with a black outline
+fn draw_root_markers(buffer: &mut [u32], view: &Scene) {
+    for &root in view.roots.iter() {
+        let (rx, ry) = view.complex_to_pixel(root);
+        let extent = ROOT_RADIUS as isize + 2;
+        for dy in -extent..=extent {
+            for dx in -extent..=extent {
+                let (x, y) = (rx.round() as isize + dx, ry.round() as isize + dy);
+                if x < 0 || y < 0 || x >= view.width as isize || y >= view.height as isize {
+                    continue;
+                }
+                let distance = ((x as f64 - rx).hypot(y as f64 - ry) - ROOT_RADIUS).abs();
+                if distance < 1.0 {
+                    buffer[x as usize + y as usize * view.width] = 0xffffff;
+                } else if distance < 2.0 {
+                    buffer[x as usize + y as usize * view.width] = 0x000000;
+                }
+            }
+        }
+    }
+}
+
+/// Writes `scene` to the first free `output/view-<n>.txt`
+fn export_scene(scene: &Scene) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all("output")?;
+    let path = (0..)
+        .map(|n| PathBuf::from(format!("output/view-{}.txt", n)))
+        .find(|path| !path.exists())
+        .unwrap();
+    scene.save(&path)?;
+    Ok(path)
+}